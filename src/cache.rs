@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::Error, NeoResult};
+
+/// Part files left untouched this long belong to downloads that were cut short
+const STALE_PART: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entry {
+    /// When the audio was last played, in seconds since the unix epoch
+    last_played: u64,
+    /// Length of the audio in seconds, if known
+    duration: Option<f64>,
+}
+
+/// Downloaded YouTube audio, kept in `$XDG_CACHE_HOME/neoplayer` so it can be
+/// played again without downloading it. The least recently played files are
+/// removed once the cache grows past its size limit
+pub struct Cache {
+    dir: PathBuf,
+    /// Maximum size of the cache, in bytes
+    limit: u64,
+    index: HashMap<String, Entry>,
+}
+
+pub struct CacheInfo {
+    pub dir: PathBuf,
+    pub files: usize,
+    /// Total size of the cached audio, in bytes
+    pub size: u64,
+    /// Maximum size of the cache, in bytes
+    pub limit: u64,
+}
+
+impl Cache {
+    /// Opens the cache, with a size limit in megabytes
+    pub fn new(limit: u64) -> NeoResult<Self> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| Error::Other(String::from("Can't find the cache directory.")))?
+            .join("neoplayer");
+        fs::create_dir_all(&dir)?;
+
+        let index = fs::read_to_string(Self::index_path(&dir))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        // Downloads interrupted by a crash leave their part file behind
+        for (path, _) in Self::parts(&dir)? {
+            let stale = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |age| age > STALE_PART);
+            if stale {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        Ok(Self {
+            dir,
+            limit: limit * 1024 * 1024,
+            index,
+        })
+    }
+
+    /// Where the audio for a video is stored
+    pub fn path(&self, video_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.mp3", video_id))
+    }
+
+    /// Returns the cached audio for a video and its duration, if it was fully downloaded before
+    pub fn get(&self, video_id: &str) -> Option<(PathBuf, Option<Duration>)> {
+        let path = self.path(video_id);
+        if path.exists() {
+            let duration = self
+                .index
+                .get(video_id)
+                .and_then(|entry| entry.duration)
+                .map(Duration::from_secs_f64);
+            Some((path, duration))
+        } else {
+            None
+        }
+    }
+
+    /// Marks a video as just played, then evicts older files if the cache is over its limit
+    pub fn touch(&mut self, video_id: &str, duration: Option<Duration>) -> NeoResult<()> {
+        let entry = self.index.entry(String::from(video_id)).or_default();
+        entry.last_played = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if let Some(duration) = duration {
+            entry.duration = Some(duration.as_secs_f64());
+        }
+
+        self.evict(video_id)?;
+        self.save()
+    }
+
+    /// Removes the least recently played files until the cache fits its limit,
+    /// never removing the audio for `keep`. Downloads in progress count towards
+    /// the limit, but are left alone
+    fn evict(&mut self, keep: &str) -> NeoResult<()> {
+        let mut files = self.files()?;
+        files.sort_by_key(|(video_id, _)| {
+            self.index
+                .get(video_id)
+                .map_or(0, |entry| entry.last_played)
+        });
+
+        let mut size: u64 = files.iter().map(|(_, size)| size).sum::<u64>()
+            + Self::parts(&self.dir)?
+                .iter()
+                .map(|(_, size)| size)
+                .sum::<u64>();
+        for (video_id, file_size) in files {
+            if size <= self.limit {
+                break;
+            }
+            if video_id != keep {
+                fs::remove_file(self.path(&video_id))?;
+                self.index.remove(&video_id);
+                size -= file_size;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes every cached file except the audio for `keep`
+    pub fn clear(&mut self, keep: Option<&str>) -> NeoResult<()> {
+        for (video_id, _) in self.files()? {
+            if Some(video_id.as_str()) != keep {
+                fs::remove_file(self.path(&video_id))?;
+                self.index.remove(&video_id);
+            }
+        }
+        self.save()
+    }
+
+    pub fn info(&self) -> NeoResult<CacheInfo> {
+        let files = self.files()?;
+        Ok(CacheInfo {
+            dir: self.dir.clone(),
+            files: files.len(),
+            size: files.iter().map(|(_, size)| size).sum(),
+            limit: self.limit,
+        })
+    }
+
+    /// Lists the fully downloaded files with their size, skipping ones still being written
+    fn files(&self) -> NeoResult<Vec<(String, u64)>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "mp3") {
+                if let Some(video_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    files.push((String::from(video_id), fs::metadata(&path)?.len()));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Lists the part files of downloads in progress with their size
+    fn parts(dir: &Path) -> NeoResult<Vec<(PathBuf, u64)>> {
+        let mut parts = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "part") {
+                // The download may finish, and rename it, while listing
+                if let Ok(metadata) = fs::metadata(&path) {
+                    parts.push((path, metadata.len()));
+                }
+            }
+        }
+        Ok(parts)
+    }
+
+    fn save(&self) -> NeoResult<()> {
+        fs::write(
+            Self::index_path(&self.dir),
+            serde_json::to_string(&self.index)?,
+        )?;
+        Ok(())
+    }
+
+    fn index_path(dir: &Path) -> PathBuf {
+        dir.join("index.json")
+    }
+}
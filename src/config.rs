@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{store, NeoResult};
+
+/// Settings that are kept between sessions
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Playback volume, from 0 to 100
+    pub volume: u8,
+    /// Maximum size of the YouTube audio cache, in megabytes
+    pub cache_size: u64,
+    /// Queue Spotify recommendations when the queue runs out
+    pub autoplay: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            cache_size: 1024,
+            autoplay: false,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config from disk, falling back to the defaults if it's missing or unreadable
+    pub fn load() -> Self {
+        store::load_json(store::config_path("config.json"))
+    }
+
+    pub fn save(&self) -> NeoResult<()> {
+        store::save_pretty_json(store::config_path("config.json"), self)
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+use crate::{store, NeoResult};
+
+/// How close to its end an episode has to be stopped to count as finished
+const FINISHED_MARGIN: Duration = Duration::from_secs(30);
+
+/// Where each podcast episode was left off, by Spotify URI, kept in
+/// `$XDG_DATA_HOME/neoplayer` so episodes resume there in later sessions
+#[derive(Default, Serialize, Deserialize)]
+pub struct EpisodePositions {
+    /// Positions in seconds
+    positions: HashMap<String, f64>,
+}
+
+impl EpisodePositions {
+    pub fn load() -> Self {
+        store::load_json(store::data_path("episodes.json"))
+    }
+
+    fn save(&self) -> NeoResult<()> {
+        store::save_json(store::data_path("episodes.json"), self)
+    }
+
+    pub fn get(&self, uri: &str) -> Option<Duration> {
+        self.positions
+            .get(uri)
+            .map(|position| Duration::from_secs_f64(*position))
+    }
+
+    /// Remembers where an episode was left off, forgetting it instead if it
+    /// was played to the end
+    pub fn set(
+        &mut self,
+        uri: &str,
+        position: Duration,
+        duration: Option<Duration>,
+    ) -> NeoResult<()> {
+        match duration {
+            Some(duration) if position + FINISHED_MARGIN >= duration => {
+                self.positions.remove(uri);
+            }
+            _ => {
+                self.positions
+                    .insert(String::from(uri), position.as_secs_f64());
+            }
+        }
+        self.save()
+    }
+}
@@ -4,6 +4,7 @@ use std::{
 };
 
 use rspotify::ClientError;
+use rspotify_model::IdError;
 use termion::event::Key;
 use tokio::sync::broadcast::error::SendError;
 
@@ -17,6 +18,7 @@ pub enum Error {
     EventSender(SendError<Event<Key>>),
     Receiver(RecvError),
     Client(ClientError),
+    Id(IdError),
     OSMediaControls(souvlaki::Error),
     Ureq(ureq::Error),
     SerdeJson(serde_json::Error),
@@ -61,6 +63,12 @@ impl From<ClientError> for Error {
     }
 }
 
+impl From<IdError> for Error {
+    fn from(source: IdError) -> Self {
+        Error::Id(source)
+    }
+}
+
 impl From<souvlaki::Error> for Error {
     fn from(source: souvlaki::Error) -> Self {
         Error::OSMediaControls(source)
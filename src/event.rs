@@ -1,15 +1,50 @@
 use librespot::{core::spotify_id::SpotifyId, playback::player::PlayerEventChannel};
-use std::{io, sync::mpsc, thread, time::Duration};
+use std::{io, path::PathBuf, sync::mpsc, thread, time::Duration};
 use termion::{event::Key, input::TermRead};
 
+use crate::{
+    library::Library,
+    local::Scan,
+    youtube::{Download, DownloadProgress, YoutubePlaylist},
+};
+
 #[derive(Debug)]
 pub enum Event<I> {
     Input(I),
     Tick,
     UpdateNP(SpotifyId),
+    /// Position and duration of the current track, as reported by librespot
+    UpdateProgress(Duration, Duration),
+    /// Mixer volume set through librespot, from 0 to `u16::MAX`
+    UpdateVolume(u16),
     TrackEnded,
     PleasePause,
     PleaseResume,
+    PleaseToggle,
+    PleaseStop,
+    PleaseNext,
+    PleasePrevious,
+    PleaseOpenUri(String),
+    PleaseQuit,
+    PleaseSeek(Duration),
+    PleaseSeekBy(i64),
+    DownloadProgress(DownloadProgress),
+    /// A YouTube video has enough audio downloaded to start playing
+    DownloadReady(String, Download),
+    /// A YouTube video couldn't be downloaded, with the reason
+    DownloadFailed(String, String),
+    /// The library was synced in the background
+    LibrarySynced(Library),
+    /// The library couldn't be synced, with the reason
+    LibrarySyncFailed(String),
+    /// Audio files were found by `scan` in the background
+    LocalScanned(Scan),
+    /// A folder couldn't be scanned, with the reason
+    LocalScanFailed(PathBuf, String),
+    /// The videos of a YouTube playlist or channel were listed in the background
+    YoutubePlaylistLoaded(YoutubePlaylist),
+    /// A YouTube playlist or channel couldn't be listed, with the reason
+    YoutubePlaylistFailed(String),
 }
 
 /// A small event handler that wrap termion input and tick events. Each event
@@ -20,8 +55,11 @@ pub struct Events {
 }
 
 impl Events {
-    pub fn new(mut player_events: PlayerEventChannel) -> Events {
-        let (tx, rx) = mpsc::channel();
+    pub fn new(
+        tx: mpsc::Sender<Event<Key>>,
+        rx: mpsc::Receiver<Event<Key>>,
+        mut player_events: PlayerEventChannel,
+    ) -> Events {
         {
             let tx = tx.clone();
             thread::spawn(move || {
@@ -57,14 +95,29 @@ impl Events {
                     }
                     librespot::playback::player::PlayerEvent::Loading { .. } => (),
                     librespot::playback::player::PlayerEvent::Preloading { .. } => (),
-                    librespot::playback::player::PlayerEvent::Playing { .. } => (),
-                    librespot::playback::player::PlayerEvent::Paused { .. } => (),
+                    librespot::playback::player::PlayerEvent::Playing {
+                        position_ms,
+                        duration_ms,
+                        ..
+                    }
+                    | librespot::playback::player::PlayerEvent::Paused {
+                        position_ms,
+                        duration_ms,
+                        ..
+                    } => tx_clone
+                        .send(Event::UpdateProgress(
+                            Duration::from_millis(position_ms as u64),
+                            Duration::from_millis(duration_ms as u64),
+                        ))
+                        .unwrap(),
                     librespot::playback::player::PlayerEvent::TimeToPreloadNextTrack { .. } => (),
                     librespot::playback::player::PlayerEvent::EndOfTrack { .. } => {
                         tx_clone.send(Event::TrackEnded).unwrap()
                     }
                     librespot::playback::player::PlayerEvent::Unavailable { .. } => (),
-                    librespot::playback::player::PlayerEvent::VolumeSet { .. } => (),
+                    librespot::playback::player::PlayerEvent::VolumeSet { volume } => {
+                        tx_clone.send(Event::UpdateVolume(volume)).unwrap()
+                    }
                 }
             }
         });
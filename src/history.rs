@@ -0,0 +1,166 @@
+use chrono::{Datelike, Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{store, NeoResult, Track, Uri};
+
+/// How many days or weeks `listening_time` goes back
+const PERIODS: usize = 14;
+
+/// A track that was played, on any platform
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Play {
+    pub track: Track,
+    /// When the track started playing, as a Unix timestamp
+    pub played_at: i64,
+    /// How long the track was heard, leaving out pauses and the parts skipped by seeking
+    pub listened: Duration,
+    /// Whether it was stopped before its end, by playing something else or quitting
+    pub skipped: bool,
+}
+
+/// What `listening_time` adds up the time listened by
+#[derive(Clone, Copy)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+/// Play counts and time listened of a track or artist
+pub struct Plays<T> {
+    pub item: T,
+    pub count: usize,
+    pub listened: Duration,
+}
+
+/// Every track played, kept in `$XDG_DATA_HOME/neoplayer` so it outlives the
+/// session. Each play is appended as a line of JSON, so recording one doesn't
+/// rewrite the whole history
+#[derive(Default)]
+pub struct ListeningHistory {
+    /// Plays, oldest first
+    plays: Vec<Play>,
+}
+
+impl ListeningHistory {
+    fn path() -> Option<PathBuf> {
+        store::data_path("history.jsonl")
+    }
+
+    pub fn load() -> Self {
+        let plays = Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| {
+                // A line cut short by a crash is skipped rather than losing the rest
+                contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { plays }
+    }
+
+    fn append(play: &Play) -> NeoResult<()> {
+        if let Some(path) = Self::path() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(play)?)?;
+        }
+        Ok(())
+    }
+
+    /// The current time, as a Unix timestamp for `Play::played_at`
+    pub fn now() -> i64 {
+        Utc::now().timestamp()
+    }
+
+    pub fn record(&mut self, play: Play) -> NeoResult<()> {
+        Self::append(&play)?;
+        self.plays.push(play);
+        Ok(())
+    }
+
+    /// Plays, most recent first
+    pub fn plays(&self) -> impl Iterator<Item = &Play> {
+        self.plays.iter().rev()
+    }
+
+    /// The tracks played the most, counting skipped plays only for the time listened
+    pub fn top_tracks(&self, count: usize) -> Vec<Plays<&Track>> {
+        let mut tracks: HashMap<&Uri, Plays<&Track>> = HashMap::new();
+        for play in &self.plays {
+            let plays = tracks.entry(&play.track.uri).or_insert(Plays {
+                item: &play.track,
+                count: 0,
+                listened: Duration::ZERO,
+            });
+            plays.count += !play.skipped as usize;
+            plays.listened += play.listened;
+        }
+        top(tracks.into_values().collect(), count)
+    }
+
+    /// The artists played the most, counting skipped plays only for the time listened
+    pub fn top_artists(&self, count: usize) -> Vec<Plays<&str>> {
+        let mut artists: HashMap<&str, Plays<&str>> = HashMap::new();
+        for play in self
+            .plays
+            .iter()
+            .filter(|play| !play.track.artist.is_empty())
+        {
+            let plays = artists.entry(play.track.artist.as_str()).or_insert(Plays {
+                item: play.track.artist.as_str(),
+                count: 0,
+                listened: Duration::ZERO,
+            });
+            plays.count += !play.skipped as usize;
+            plays.listened += play.listened;
+        }
+        top(artists.into_values().collect(), count)
+    }
+
+    /// Time listened in each of the last days or weeks that had any, most recent
+    /// first, labelled with the day or the ISO week in local time
+    pub fn listening_time(&self, period: Period) -> Vec<(String, Duration)> {
+        let mut periods: Vec<(String, Duration)> = vec![];
+        for play in self.plays() {
+            let date = match Local.timestamp_opt(play.played_at, 0).single() {
+                Some(time) => time.naive_local().date(),
+                None => continue,
+            };
+            let label = match period {
+                Period::Day => date.format("%Y-%m-%d").to_string(),
+                Period::Week => {
+                    format!("{}-W{:02}", date.iso_week().year(), date.iso_week().week())
+                }
+            };
+            match periods.last_mut() {
+                Some((last, listened)) if *last == label => *listened += play.listened,
+                _ if periods.len() == PERIODS => break,
+                _ => periods.push((label, play.listened)),
+            }
+        }
+        periods
+    }
+}
+
+/// Sorts by play count, then time listened, keeping the first `count`
+fn top<T>(mut plays: Vec<Plays<T>>, count: usize) -> Vec<Plays<T>> {
+    plays.retain(|plays| plays.count > 0);
+    plays.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.listened.cmp(&a.listened))
+    });
+    plays.truncate(count);
+    plays
+}
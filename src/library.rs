@@ -0,0 +1,119 @@
+use rspotify_model::{track::SavedTrack as SpotifySavedTrack, Id};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, time::Duration};
+
+use crate::{spotify::SpotifyClient, store, NeoResult};
+
+/// How many saved tracks are fetched per request while syncing
+const SYNC_PAGE: u32 = 50;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedTrack {
+    /// Spotify track URI
+    pub uri: String,
+    pub name: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: Duration,
+    /// When the track was saved, as an RFC 3339 timestamp
+    pub added_at: String,
+}
+
+impl SavedTrack {
+    fn from_spotify(saved: SpotifySavedTrack) -> Option<Self> {
+        let track = saved.track;
+        Some(Self {
+            uri: track.id?.uri(),
+            artist: track
+                .artists
+                .first()
+                .map(|artist| artist.name.clone())
+                .unwrap_or_default(),
+            name: track.name,
+            album: track.album.name,
+            duration: track.duration,
+            added_at: saved.added_at.to_rfc3339(),
+        })
+    }
+}
+
+/// The tracks saved in the user's Spotify library, kept in
+/// `$XDG_DATA_HOME/neoplayer` so they can be browsed before the API answers
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Library {
+    /// Saved tracks, most recently saved first
+    pub tracks: Vec<SavedTrack>,
+    /// Whether the library was ever synced, as opposed to just being empty
+    pub synced: bool,
+}
+
+impl Library {
+    pub fn load() -> Self {
+        store::load_json(store::data_path("library.json"))
+    }
+
+    pub fn save(&self) -> NeoResult<()> {
+        store::save_json(store::data_path("library.json"), self)
+    }
+
+    /// Removes a track unsaved from this app, so the sync doesn't have to find out
+    pub fn remove(&mut self, uri: &str) {
+        self.tracks.retain(|track| track.uri != uri);
+    }
+
+    /// Fetches the tracks saved since the last sync. The API lists the most
+    /// recently saved tracks first, so that stops at the first one already known.
+    /// If the library then doesn't add up to the total the API reports, tracks
+    /// were unsaved from another app in the meantime, which the API only tells
+    /// by fetching the whole library again
+    pub async fn sync(mut self, client: &SpotifyClient) -> NeoResult<Self> {
+        let known = self
+            .tracks
+            .iter()
+            .map(|track| (track.uri.clone(), track.added_at.clone()))
+            .collect::<HashSet<(String, String)>>();
+
+        let mut new = vec![];
+        let mut offset = 0;
+        let total = loop {
+            let (page, total) = client.get_library(offset, SYNC_PAGE).await?;
+            offset += page.len() as u32;
+            let last = page.is_empty() || offset >= total;
+
+            let mut reached_known = false;
+            for track in page.into_iter().filter_map(SavedTrack::from_spotify) {
+                if known.contains(&(track.uri.clone(), track.added_at.clone())) {
+                    reached_known = true;
+                    break;
+                }
+                new.push(track);
+            }
+            if reached_known || last {
+                break total;
+            }
+        };
+
+        if new.len() + self.tracks.len() == total as usize {
+            new.append(&mut self.tracks);
+            self.tracks = new;
+        } else {
+            self.tracks = Self::fetch_all(client).await?;
+        }
+        self.synced = true;
+        Ok(self)
+    }
+
+    async fn fetch_all(client: &SpotifyClient) -> NeoResult<Vec<SavedTrack>> {
+        let mut tracks = vec![];
+        let mut offset = 0;
+        loop {
+            let (page, total) = client.get_library(offset, SYNC_PAGE).await?;
+            offset += page.len() as u32;
+            let last = page.is_empty() || offset >= total;
+            tracks.extend(page.into_iter().filter_map(SavedTrack::from_spotify));
+            if last {
+                return Ok(tracks);
+            }
+        }
+    }
+}
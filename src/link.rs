@@ -0,0 +1,186 @@
+/// Kinds of Spotify URIs that can be opened
+const SPOTIFY_KINDS: [&str; 6] = ["track", "episode", "album", "artist", "playlist", "show"];
+
+/// What a link or URI pasted into the input, or sent through MPRIS, points to
+#[derive(Debug, PartialEq)]
+pub enum Link {
+    /// A Spotify URI such as `spotify:track:<id>`, including the ones made from
+    /// `open.spotify.com` links
+    Spotify(String),
+    /// A YouTube video ID
+    YoutubeVideo(String),
+    /// A YouTube playlist ID
+    YoutubePlaylist(String),
+}
+
+impl Link {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(rest) = input.strip_prefix("spotify:") {
+            let (kind, id) = rest.split_once(':')?;
+            return spotify(kind, id);
+        }
+
+        let input = input
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .trim_start_matches("m.")
+            .trim_start_matches("music.");
+        let (host, path) = input.split_once('/')?;
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let segments = path.split('/').collect::<Vec<&str>>();
+
+        match host {
+            "open.spotify.com" => match segments.as_slice() {
+                // Links shared from some regions start with the locale, like `intl-de`
+                [locale, kind, id, ..] if locale.starts_with("intl-") => spotify(kind, id),
+                [kind, id, ..] => spotify(kind, id),
+                _ => None,
+            },
+            "youtu.be" => youtube_id(segments.first()?).map(Link::YoutubeVideo),
+            "youtube.com" => match segments.as_slice() {
+                ["watch"] => youtube_id(param(query, "v")?).map(Link::YoutubeVideo),
+                ["playlist"] => youtube_id(param(query, "list")?).map(Link::YoutubePlaylist),
+                ["shorts", id] | ["embed", id] | ["live", id] => {
+                    youtube_id(id).map(Link::YoutubeVideo)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn spotify(kind: &str, id: &str) -> Option<Link> {
+    if SPOTIFY_KINDS.contains(&kind) && !id.is_empty() {
+        Some(Link::Spotify(format!("spotify:{}:{}", kind, id)))
+    } else {
+        None
+    }
+}
+
+/// Gets the value of a parameter in a URL query string
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Checks that a video or playlist ID only has the characters YouTube uses
+fn youtube_id(id: &str) -> Option<String> {
+    if !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Some(String::from(id))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(uri: &str) -> Option<Link> {
+        Some(Link::Spotify(String::from(uri)))
+    }
+
+    fn video(id: &str) -> Option<Link> {
+        Some(Link::YoutubeVideo(String::from(id)))
+    }
+
+    #[test]
+    fn spotify_uris() {
+        assert_eq!(
+            Link::parse("spotify:track:4uLU6hMCjMI75M1A2tKUQC"),
+            uri("spotify:track:4uLU6hMCjMI75M1A2tKUQC")
+        );
+        assert_eq!(
+            Link::parse("  spotify:show:5CfCWKI5pZ28U0uOzXkDHe\n"),
+            uri("spotify:show:5CfCWKI5pZ28U0uOzXkDHe")
+        );
+    }
+
+    #[test]
+    fn spotify_links() {
+        assert_eq!(
+            Link::parse("https://open.spotify.com/album/1DFixLWuPkv3KT3TnV35m3"),
+            uri("spotify:album:1DFixLWuPkv3KT3TnV35m3")
+        );
+        assert_eq!(
+            Link::parse("https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M?si=abc123"),
+            uri("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M")
+        );
+        assert_eq!(
+            Link::parse("open.spotify.com/intl-de/artist/0OdUWJ0sBjDrqHygGUXeCF"),
+            uri("spotify:artist:0OdUWJ0sBjDrqHygGUXeCF")
+        );
+        assert_eq!(
+            Link::parse("http://open.spotify.com/episode/512ojhOuo1ktJprKbVcKyQ"),
+            uri("spotify:episode:512ojhOuo1ktJprKbVcKyQ")
+        );
+    }
+
+    #[test]
+    fn youtube_videos() {
+        assert_eq!(
+            Link::parse("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            video("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            Link::parse("https://m.youtube.com/watch?feature=share&v=dQw4w9WgXcQ&t=42"),
+            video("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            Link::parse("https://music.youtube.com/watch?v=dQw4w9WgXcQ"),
+            video("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            Link::parse("https://youtu.be/dQw4w9WgXcQ?t=42"),
+            video("dQw4w9WgXcQ")
+        );
+        assert_eq!(Link::parse("youtube.com/shorts/a1_B-c2"), video("a1_B-c2"));
+        assert_eq!(
+            Link::parse("https://www.youtube.com/embed/dQw4w9WgXcQ"),
+            video("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            Link::parse("https://www.youtube.com/live/dQw4w9WgXcQ"),
+            video("dQw4w9WgXcQ")
+        );
+    }
+
+    #[test]
+    fn youtube_playlists() {
+        assert_eq!(
+            Link::parse("https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI"),
+            Some(Link::YoutubePlaylist(String::from(
+                "PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI"
+            )))
+        );
+    }
+
+    #[test]
+    fn rejects() {
+        assert_eq!(Link::parse(""), None);
+        assert_eq!(Link::parse("never gonna give you up"), None);
+        assert_eq!(Link::parse("spotify:user:someone"), None);
+        assert_eq!(Link::parse("spotify:track:"), None);
+        assert_eq!(Link::parse("https://open.spotify.com/track"), None);
+        assert_eq!(
+            Link::parse("https://www.youtube.com/watch?list=PL123"),
+            None
+        );
+        assert_eq!(
+            Link::parse("https://www.youtube.com/watch?v=<script>"),
+            None
+        );
+        assert_eq!(Link::parse("https://www.youtube.com/@channel"), None);
+        assert_eq!(Link::parse("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+    }
+}
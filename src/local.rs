@@ -0,0 +1,214 @@
+use lofty::{Accessor, AudioFile};
+use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{error::Error, player::new_sink, store, NeoResult};
+
+/// File extensions rodio is able to decode
+const EXTENSIONS: [&str; 4] = ["flac", "mp3", "ogg", "wav"];
+
+pub struct LocalPlayer {
+    pub sink: Sink,
+    handle: OutputStreamHandle,
+    /// File currently loaded in the sink
+    file: Option<PathBuf>,
+    /// Length of the file currently loaded in the sink
+    pub duration: Option<Duration>,
+}
+
+impl LocalPlayer {
+    pub fn new(output_stream_handle: OutputStreamHandle) -> NeoResult<Self> {
+        Ok(Self {
+            sink: Sink::try_new(&output_stream_handle)
+                .map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))?,
+            handle: output_stream_handle,
+            file: None,
+            duration: None,
+        })
+    }
+
+    pub fn play(&mut self, path: PathBuf) -> NeoResult<()> {
+        let source = decode(&path)?;
+
+        let sink = new_sink(&self.handle, &self.sink)?;
+        sink.append(source);
+        sink.play();
+
+        self.sink = sink;
+        self.duration = LocalTrack::read(&path).map(|track| track.duration);
+        self.file = Some(path);
+        Ok(())
+    }
+
+    pub fn seek(&mut self, position: Duration) -> NeoResult<()> {
+        if let Some(path) = &self.file {
+            let source = decode(path)?;
+
+            let sink = new_sink(&self.handle, &self.sink)?;
+            sink.append(source.skip_duration(position));
+
+            self.sink = sink;
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.sink.pause();
+    }
+
+    /// Replaces the sink with an empty one, unloading the file
+    pub fn stop(&mut self) -> NeoResult<()> {
+        self.sink = new_sink(&self.handle, &self.sink)?;
+        self.file = None;
+        self.duration = None;
+        Ok(())
+    }
+
+    pub fn resume(&mut self) {
+        self.sink.play();
+    }
+
+    /// Sets the sink volume from a 0-100 percentage
+    pub fn set_volume(&mut self, volume: u8) {
+        self.sink.set_volume(volume as f32 / 100.0);
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.sink.empty()
+    }
+}
+
+fn decode(path: &Path) -> NeoResult<Decoder<BufReader<File>>> {
+    Decoder::new(BufReader::new(File::open(path)?))
+        .map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocalTrack {
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: Duration,
+}
+
+impl LocalTrack {
+    /// Reads the tags of an audio file, using the file name when it has no title
+    fn read(path: &Path) -> Option<Self> {
+        let file = lofty::read_from_path(path, true).ok()?;
+        let tag = file.primary_tag().or_else(|| file.first_tag());
+
+        Some(Self {
+            path: path.to_path_buf(),
+            title: tag
+                .and_then(|tag| tag.title())
+                .map(String::from)
+                .or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))?,
+            artist: tag
+                .and_then(|tag| tag.artist())
+                .map(String::from)
+                .unwrap_or_default(),
+            album: tag
+                .and_then(|tag| tag.album())
+                .map(String::from)
+                .unwrap_or_default(),
+            duration: file.properties().duration(),
+        })
+    }
+}
+
+/// Audio files found under a directory by `LocalLibrary::scan`
+#[derive(Debug)]
+pub struct Scan {
+    /// The directory scanned, canonicalized
+    pub dir: PathBuf,
+    pub tracks: Vec<LocalTrack>,
+    /// Subdirectories that couldn't be read, and were left out
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Tags of the audio files indexed with `scan`, kept in `$XDG_DATA_HOME/neoplayer`
+#[derive(Default, Serialize, Deserialize)]
+pub struct LocalLibrary {
+    tracks: Vec<LocalTrack>,
+}
+
+impl LocalLibrary {
+    pub fn load() -> Self {
+        store::load_json(store::data_path("local.json"))
+    }
+
+    fn save(&self) -> NeoResult<()> {
+        store::save_json(store::data_path("local.json"), self)
+    }
+
+    /// Finds every audio file under `dir` and reads its tags, which blocks for a
+    /// while on large folders. The result is indexed with `add`
+    pub fn scan(dir: &Path) -> NeoResult<Scan> {
+        let mut scan = Scan {
+            dir: dir.canonicalize()?,
+            tracks: vec![],
+            skipped: vec![],
+        };
+        Self::walk(&scan.dir.clone(), &mut scan)?;
+        Ok(scan)
+    }
+
+    /// Indexes the tracks found by `scan`, replacing what was indexed under its
+    /// directory before
+    pub fn add(&mut self, scan: Scan) -> NeoResult<()> {
+        self.tracks
+            .retain(|track| !track.path.starts_with(&scan.dir));
+        self.tracks.extend(scan.tracks);
+        self.tracks
+            .sort_by(|a, b| (&a.artist, &a.album, &a.path).cmp(&(&b.artist, &b.album, &b.path)));
+        self.save()
+    }
+
+    fn walk(dir: &Path, scan: &mut Scan) -> io::Result<()> {
+        for entry in fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            // Symlinked folders aren't followed, since one linking to a parent would never end
+            if entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_dir())
+            {
+                if Self::walk(&path, scan).is_err() {
+                    scan.skipped.push(path);
+                }
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| {
+                    EXTENSIONS.contains(&ext.to_lowercase().as_str())
+                })
+            {
+                scan.tracks.extend(LocalTrack::read(&path));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&LocalTrack> {
+        self.tracks.iter().find(|track| track.path == path)
+    }
+
+    /// Finds the tracks whose title, artist or album contain the query, ignoring case
+    pub fn search(&self, query: &str) -> Vec<&LocalTrack> {
+        let query = query.to_lowercase();
+        self.tracks
+            .iter()
+            .filter(|track| {
+                [&track.title, &track.artist, &track.album]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(&query))
+            })
+            .collect()
+    }
+}
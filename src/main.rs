@@ -1,51 +1,139 @@
+mod cache;
+mod config;
+mod episodes;
 mod error;
 mod event;
+mod history;
+mod library;
+mod link;
+mod local;
 mod player;
 mod spotify;
+mod store;
 mod widgets;
 mod youtube;
 
 use crate::{
+    cache::Cache,
+    config::Config,
     error::Error,
     event::{Event, Events},
 };
+use episodes::EpisodePositions;
 use futures::future::join_all;
-use librespot::metadata::Metadata;
+use history::{ListeningHistory, Period, Play};
+use library::Library;
+use librespot::{
+    core::spotify_id::{SpotifyAudioType, SpotifyId},
+    metadata::Metadata as _,
+};
+use link::Link;
+use local::LocalLibrary;
 use player::Player;
-use rspotify_model::Id;
-use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, PlatformConfig};
+use rand::Rng;
+use rspotify_model::{
+    album::SimplifiedAlbum,
+    artist::FullArtist,
+    enums::{misc::TimeRange, types::SearchType},
+    playlist::SimplifiedPlaylist,
+    search::SearchResult,
+    show::SimplifiedShow,
+    track::{FullTrack, SimplifiedTrack},
+    Id, PlayableItem,
+};
+use serde::{Deserialize, Serialize};
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+    SeekDirection,
+};
 use spotify::{SpotifyClient, SpotifyPlayer};
-use std::{borrow::BorrowMut, collections::HashSet, fmt::Display, io, iter::FromIterator};
+use std::{
+    borrow::BorrowMut,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    io,
+    iter::FromIterator,
+    path::PathBuf,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{
     backend::TermionBackend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Span, Spans},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, LineGauge, List, ListItem, Paragraph},
     Terminal,
 };
 use unicode_width::UnicodeWidthStr;
-use widgets::StatefulList;
-use youtube::YoutubeClient;
+use widgets::{format_duration, StatefulList};
+use youtube::{DownloadProgress, YoutubeClient, YoutubeResult};
 
 pub type NeoResult<T> = Result<T, Error>;
 
+/// How far the seek keys and the MPRIS `Seek` event move playback, in milliseconds
+const SEEK_STEP: i64 = 5000;
+
+/// How much the volume keys change the volume, in percent
+const VOLUME_STEP: u8 = 5;
+
+/// How many of the last played tracks autoplay looks at, both to seed the
+/// recommendations (the first 5 Spotify ones) and to avoid repeating them
+const AUTOPLAY_HISTORY: usize = 50;
+
+/// How many saved tracks are fetched at a time in the library view
+const LIBRARY_PAGE: u32 = 50;
+
+/// How close to the end of a paged list the selection gets before the next
+/// page is fetched
+const LOAD_AHEAD: usize = 10;
+
+/// How often the position of the podcast episode being played is saved
+const EPISODE_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
 enum InputMode {
     Normal,
     Editing,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Repeat {
+    Off,
+    /// Play the current track again when it ends
+    One,
+    /// Send tracks to the back of the queue when they end
+    All,
+}
+
+impl Repeat {
+    fn next(self) -> Self {
+        match self {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
+        }
+    }
+}
+
 /// App holds the state of the application
 struct App {
     /// Current value of the input box
     input: String,
     /// Current input mode
     input_mode: InputMode,
-    /// Search results
-    results: (StatefulList<Track>, Platform),
+    /// Search results, or the tracks and playlists being browsed
+    results: StatefulList<Item>,
+    /// Title of the results list, naming what's being shown
+    results_title: String,
+    view: View,
+    /// Lists that albums, artists and playlists were opened from, most recent last
+    back: Vec<Page>,
     /// Currently playing song
     np: String,
+    /// Progress of the YouTube video being downloaded before it starts
+    download: Option<DownloadProgress>,
     /// Spotify client
     client: SpotifyClient,
     ///Spotify Player
@@ -55,23 +143,88 @@ struct App {
     current: Option<Track>,
     toggle_queue: bool,
     paused: bool,
+    repeat: Repeat,
+    /// Pick the next track from the queue at random
+    shuffle: bool,
     player: Player,
     os_media_controls: Option<MediaControls>,
+    /// Settings persisted between sessions
+    config: Config,
+    /// Local audio files indexed with `scan`
+    local: LocalLibrary,
+    /// Saved Spotify tracks, as of the last sync
+    library: Library,
+    /// Whether the library is being synced in the background
+    syncing: bool,
+    /// Whether another sync was asked for while one was running
+    resync: bool,
+    /// Tracks unsaved while the library was being synced, to remove from its result
+    unsaved_while_syncing: Vec<String>,
+    /// Whether Spotify tracks are saved in the library, by URI, for the ones checked so far
+    saved: HashMap<String, bool>,
+    /// Where podcast episodes were left off
+    episodes: EpisodePositions,
+    /// When the position of the current episode was last saved
+    episode_saved: Instant,
+    /// Every track played, in this session and the ones before
+    listening: ListeningHistory,
+    /// When the current track started playing, until it's written to `listening`
+    played_at: Option<i64>,
+    /// Sends events from the tasks the app starts
+    tx: mpsc::Sender<Event<Key>>,
+    /// Metadata of the current track, and the last one sent to the OS media
+    /// controls, so it's only sent again when it changes
+    metadata: Option<Metadata>,
+    sent_metadata: Option<Metadata>,
+    /// Message shown next to the input box, such as the result of the last command
+    status: String,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Platform {
     Spotify,
     Youtube,
+    Local,
 }
 
-#[derive(Clone, Debug)]
+impl Platform {
+    fn icon(&self) -> &'static str {
+        match self {
+            Platform::Spotify => "",
+            Platform::Youtube => "",
+            Platform::Local => "",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Platform::Spotify => Color::LightGreen,
+            Platform::Youtube => Color::LightRed,
+            Platform::Local => Color::LightBlue,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Uri {
     Spotify(String),
+    /// Spotify podcast episode URI
+    Episode(String),
     Youtube(String),
+    Local(PathBuf),
 }
 
-#[derive(Clone, Debug)]
+impl Uri {
+    fn platform(&self) -> Platform {
+        match self {
+            Uri::Spotify(_) | Uri::Episode(_) => Platform::Spotify,
+            Uri::Youtube(_) => Platform::Youtube,
+            Uri::Local(_) => Platform::Local,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Track {
     /// Track title
     name: String,
@@ -79,143 +232,1666 @@ pub struct Track {
     artist: String,
     /// Track URI
     uri: Uri,
+    /// Track length, when it's known before playing it
+    duration: Option<Duration>,
 }
 
 impl Display for Track {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} - {}", self.name, self.artist)
+        write!(f, "{} - {}", self.name, self.artist)?;
+        if let Some(duration) = self.duration {
+            write!(f, " ({})", format_duration(duration))?;
+        }
+        Ok(())
     }
 }
 
 impl Track {
     fn new(name: String, artist: String, uri: Uri) -> Self {
-        Self { name, artist, uri }
+        Self {
+            name,
+            artist,
+            uri,
+            duration: None,
+        }
+    }
+
+    /// Converts a track from the Web API, which has no ID if it's a local file
+    fn from_spotify(track: FullTrack) -> Option<Self> {
+        Some(Self::new(
+            track.name,
+            track.artists.first()?.name.clone(),
+            Uri::Spotify(track.id?.uri()),
+        ))
+    }
+
+    /// Converts a playlist item, which can be a track or a podcast episode
+    fn from_playable(item: PlayableItem) -> Option<Self> {
+        match item {
+            PlayableItem::Track(track) => Self::from_spotify(track),
+            PlayableItem::Episode(episode) => Some(Self::new(
+                episode.name,
+                episode.show.name,
+                Uri::Episode(episode.id.uri()),
+            )),
+        }
+    }
+
+    fn from_simplified(track: SimplifiedTrack) -> Option<Self> {
+        Some(Self::new(
+            track.name,
+            track.artists.first()?.name.clone(),
+            Uri::Spotify(track.id?.uri()),
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Album {
+    name: String,
+    artist: String,
+    /// Spotify album URI
+    uri: String,
+    /// Spotify URI of the album's main artist
+    artist_uri: Option<String>,
+}
+
+impl Album {
+    fn from_spotify(album: SimplifiedAlbum) -> Option<Self> {
+        let artist = album.artists.first();
+        Some(Self {
+            artist: artist.map(|artist| artist.name.clone()).unwrap_or_default(),
+            artist_uri: artist.and_then(|artist| Some(artist.id.as_ref()?.uri())),
+            name: album.name,
+            uri: album.id?.uri(),
+        })
+    }
+}
+
+impl Display for Album {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.name, self.artist)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Artist {
+    name: String,
+    /// Spotify artist URI
+    uri: String,
+}
+
+impl From<FullArtist> for Artist {
+    fn from(artist: FullArtist) -> Self {
+        Self {
+            name: artist.name,
+            uri: artist.id.uri(),
+        }
+    }
+}
+
+/// A results list as it was when something was opened from it
+struct Page {
+    title: String,
+    items: Vec<Item>,
+    view: View,
+    selected: Option<usize>,
+}
+
+/// What the results list is showing, so it can be reloaded after an edit
+#[derive(Clone)]
+enum View {
+    Search,
+    /// Spotify search results, which are loaded a page at a time
+    SpotifySearch {
+        query: String,
+        search_type: SearchType,
+        /// Offset of the next page, if there is one
+        next: Option<u32>,
+    },
+    /// YouTube search results, with the token to fetch the next page if there is one
+    YoutubeSearch {
+        continuation: Option<String>,
+    },
+    /// The saved tracks, which are loaded a page at a time
+    Library {
+        /// How many saved tracks were fetched so far
+        loaded: u32,
+        /// How many tracks the library has, once the first page was fetched
+        total: Option<u32>,
+    },
+    Playlists,
+    /// The tracks of a playlist, with the position of each one in the playlist,
+    /// since its episodes and local files aren't listed
+    Playlist(Playlist, Vec<u32>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Playlist {
+    name: String,
+    /// Display name of the user who owns the playlist
+    owner: String,
+    /// Number of items in the playlist
+    tracks: u32,
+    /// Spotify playlist URI
+    uri: String,
+    /// Version of the playlist that the positions in `View::Playlist` refer to
+    snapshot_id: String,
+}
+
+impl From<SimplifiedPlaylist> for Playlist {
+    fn from(playlist: SimplifiedPlaylist) -> Self {
+        Self {
+            name: playlist.name,
+            owner: playlist
+                .owner
+                .display_name
+                .unwrap_or_else(|| String::from(playlist.owner.id.id())),
+            tracks: playlist.tracks.total,
+            uri: playlist.id.uri(),
+            snapshot_id: playlist.snapshot_id,
+        }
+    }
+}
+
+impl Display for Playlist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} by {} ({} tracks)",
+            self.name, self.owner, self.tracks
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Show {
+    name: String,
+    publisher: String,
+    /// Spotify show URI
+    uri: String,
+}
+
+impl From<SimplifiedShow> for Show {
+    fn from(show: SimplifiedShow) -> Self {
+        Self {
+            name: show.name,
+            publisher: show.publisher,
+            uri: show.id.uri(),
+        }
+    }
+}
+
+/// A row of the results list
+#[derive(Clone, Debug)]
+pub enum Item {
+    Track(Track),
+    Playlist(Playlist),
+    Album(Album),
+    Artist(Artist),
+    Show(Show),
+    /// A line of listening statistics, which can't be played or opened
+    Stat(String),
+}
+
+impl Item {
+    /// Where the item is played from. Everything but tracks comes from Spotify
+    fn platform(&self) -> Platform {
+        match self {
+            Item::Track(track) => track.uri.platform(),
+            _ => Platform::Spotify,
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Item::Track(track) => track.fmt(f),
+            Item::Playlist(playlist) => write!(f, "[Playlist] {}", playlist),
+            Item::Album(album) => write!(f, "[Album] {}", album),
+            Item::Artist(artist) => write!(f, "[Artist] {}", artist.name),
+            Item::Show(show) => write!(f, "[Podcast] {} - {}", show.name, show.publisher),
+            Item::Stat(line) => f.write_str(line),
+        }
     }
 }
 
+/// Details about the current track sent to the OS media controls
+#[derive(Clone, Debug, PartialEq)]
+struct Metadata {
+    title: String,
+    artist: String,
+    album: Option<String>,
+    cover_url: Option<String>,
+    duration: Option<Duration>,
+}
+
 enum Command {
     /// Unknown Command
     Unknown,
-    /// Search spotify for the provided query
-    Search(String),
+    /// Search spotify for tracks, albums, artists, playlists or shows matching the provided query
+    Search(SearchType, String),
     /// Play the first track returned by spotify for the provided query
     Play(String),
+    /// Play or open what a Spotify or YouTube link or URI points to
+    Open(Link),
     /// Get the songs saved in the user's library
     Library,
+    /// Get the tracks the user played last on spotify
+    Recent,
+    /// List the tracks played on any platform, most recent first
+    History,
+    /// Show the most played tracks and artists and the time listened by day or week
+    Stats(Period),
+    /// Get the tracks or artists the user listened to the most over a time range
+    Top(SearchType, TimeRange),
     Pause,
     YTSearch(String),
+    /// Search spotify and youtube for tracks matching the provided query, in a single list
+    Find(String),
+    /// List the videos of a YouTube playlist or channel
+    YTPlaylist(String),
+    /// Set the playback volume, from 0 to 100
+    Volume(u8),
+    /// Toggle queueing recommendations when the queue runs out
+    Autoplay,
+    /// Show how much space the YouTube audio cache is using
+    CacheInfo,
+    /// Remove all the audio in the YouTube cache
+    CacheClear,
+    /// Index the audio files in the provided directory
+    Scan(String),
+    /// Search the indexed audio files for the provided query
+    Local(String),
+    /// Save the current track to the library
+    Like,
+    /// Remove the current track from the library
+    Unlike,
+    /// List the user's playlists
+    Playlists,
+    /// Create a playlist with the provided name
+    PlaylistNew(String),
+    /// Add the selected track to the playlist with the provided name
+    PlaylistAdd(String),
+    /// Add every track in the queue to the playlist with the provided name
+    PlaylistAddQueue(String),
+    /// Remove the selected track from the open playlist
+    PlaylistRemove,
+    /// Rename the open playlist
+    PlaylistRename(String),
+    /// Move the selected track of the open playlist to the provided position
+    PlaylistMove(usize),
 }
 
 impl From<String> for Command {
     fn from(command: String) -> Self {
+        if let Some(link) = Link::parse(&command) {
+            return Self::Open(link);
+        }
         let (prefix, command) = if let Some(split) = command.as_str().split_once(' ') {
             split
         } else {
             (command.as_str(), "")
         };
         match prefix {
-            "search" => Self::Search(String::from(command)),
-            "play" => Self::Play(String::from(command)),
+            "search" => {
+                let (search_type, query) = match command.split_once(' ') {
+                    Some(("track", query)) => (SearchType::Track, query),
+                    Some(("album", query)) => (SearchType::Album, query),
+                    Some(("artist", query)) => (SearchType::Artist, query),
+                    Some(("playlist", query)) => (SearchType::Playlist, query),
+                    Some(("show", query)) => (SearchType::Show, query),
+                    _ => (SearchType::Track, command),
+                };
+                Self::Search(search_type, String::from(query))
+            }
+            "play" => {
+                Link::parse(command).map_or_else(|| Self::Play(String::from(command)), Self::Open)
+            }
             "library" => Self::Library,
+            "recent" => Self::Recent,
+            "history" => Self::History,
+            "stats" => match command.trim() {
+                "" | "day" => Self::Stats(Period::Day),
+                "week" => Self::Stats(Period::Week),
+                _ => Self::Unknown,
+            },
+            "top" => {
+                let mut words = command.split_whitespace().peekable();
+                let search_type = match words.peek() {
+                    Some(&"tracks") => Some(SearchType::Track),
+                    Some(&"artists") => Some(SearchType::Artist),
+                    _ => None,
+                };
+                if search_type.is_some() {
+                    words.next();
+                }
+                let time_range = match words.next() {
+                    Some("short") => Some(TimeRange::ShortTerm),
+                    Some("medium") | None => Some(TimeRange::MediumTerm),
+                    Some("long") => Some(TimeRange::LongTerm),
+                    Some(_) => None,
+                };
+                match (time_range, words.next()) {
+                    (Some(time_range), None) => {
+                        Self::Top(search_type.unwrap_or(SearchType::Track), time_range)
+                    }
+                    _ => Self::Unknown,
+                }
+            }
             "pause" => Self::Pause,
             "ytsearch" => Self::YTSearch(String::from(command)),
+            "find" => Self::Find(String::from(command)),
+            "ytplaylist" => Self::YTPlaylist(String::from(command.trim())),
+            "volume" => command.trim().parse().map_or(Self::Unknown, Self::Volume),
+            "autoplay" => Self::Autoplay,
+            "cache" => match command.trim() {
+                "info" => Self::CacheInfo,
+                "clear" => Self::CacheClear,
+                _ => Self::Unknown,
+            },
+            "scan" => Self::Scan(String::from(command)),
+            "local" => Self::Local(String::from(command)),
+            "like" => Self::Like,
+            "unlike" => Self::Unlike,
+            "playlists" => Self::Playlists,
+            "playlist" => {
+                let (action, name) = command.split_once(' ').unwrap_or((command, ""));
+                let name = String::from(name.trim());
+                match action {
+                    "new" => Self::PlaylistNew(name),
+                    "add" => Self::PlaylistAdd(name),
+                    "addqueue" => Self::PlaylistAddQueue(name),
+                    "remove" => Self::PlaylistRemove,
+                    "rename" => Self::PlaylistRename(name),
+                    "move" => name.parse().map_or(Self::Unknown, Self::PlaylistMove),
+                    _ => Self::Unknown,
+                }
+            }
             _ => Self::Unknown,
         }
     }
 }
 
+/// Converts Spotify search results to items, along with a title for them and the
+/// offset of the next page, if there is one
+fn search_items(result: SearchResult) -> (&'static str, Vec<Item>, Option<u32>) {
+    fn next<T>(page: &rspotify_model::page::Page<T>) -> Option<u32> {
+        // The API doesn't return results past the first thousand
+        page.next
+            .as_ref()
+            .map(|_| page.offset + page.items.len() as u32)
+            .filter(|next| *next < 1000)
+    }
+
+    match result {
+        SearchResult::Tracks(page) => {
+            let next = next(&page);
+            let items = page
+                .items
+                .into_iter()
+                .filter_map(Track::from_spotify)
+                .map(Item::Track);
+            ("Tracks", items.collect(), next)
+        }
+        SearchResult::Albums(page) => {
+            let next = next(&page);
+            let items = page
+                .items
+                .into_iter()
+                .filter_map(Album::from_spotify)
+                .map(Item::Album);
+            ("Albums", items.collect(), next)
+        }
+        SearchResult::Artists(page) => {
+            let next = next(&page);
+            let items = page
+                .items
+                .into_iter()
+                .map(|artist| Item::Artist(artist.into()));
+            ("Artists", items.collect(), next)
+        }
+        SearchResult::Playlists(page) => {
+            let next = next(&page);
+            let items = page
+                .items
+                .into_iter()
+                .map(|playlist| Item::Playlist(playlist.into()));
+            ("Playlists", items.collect(), next)
+        }
+        SearchResult::Shows(page) => {
+            let next = next(&page);
+            let items = page.items.into_iter().map(|show| Item::Show(show.into()));
+            ("Podcasts", items.collect(), next)
+        }
+        SearchResult::Episodes(_) => ("Episodes", vec![], None),
+    }
+}
+
+/// Fetches the metadata of a Spotify track from librespot
+async fn track_metadata(
+    session: &librespot::core::session::Session,
+    track: SpotifyId,
+) -> NeoResult<Metadata> {
+    let data = librespot::metadata::Track::get(session, track).await?;
+    let album = librespot::metadata::Album::get(session, data.album).await?;
+    // Artists that can't be fetched are left out rather than failing the whole track
+    let artists = join_all(
+        data.artists
+            .iter()
+            .map(|id| librespot::metadata::Artist::get(session, *id)),
+    )
+    .await
+    .into_iter()
+    .filter_map(|artist| Some(artist.ok()?.name))
+    .collect::<Vec<String>>()
+    .join(", ");
+
+    Ok(Metadata {
+        title: data.name,
+        artist: artists,
+        album: Some(album.name),
+        cover_url: album
+            .covers
+            .first()
+            .map(|cover| format!("https://i.scdn.co/image/{}", cover.to_base16())),
+        duration: Some(Duration::from_millis(data.duration as u64)),
+    })
+}
+
+/// Fetches the metadata of a podcast episode from librespot, with its show as the album
+async fn episode_metadata(
+    session: &librespot::core::session::Session,
+    episode: SpotifyId,
+) -> NeoResult<Metadata> {
+    let data = librespot::metadata::Episode::get(session, episode).await?;
+    let show = librespot::metadata::Show::get(session, data.show).await?;
+
+    Ok(Metadata {
+        title: data.name,
+        artist: show.publisher,
+        cover_url: data
+            .covers
+            .first()
+            .or_else(|| show.covers.first())
+            .map(|cover| format!("https://i.scdn.co/image/{}", cover.to_base16())),
+        album: Some(show.name),
+        duration: Some(Duration::from_millis(data.duration as u64)),
+    })
+}
+
+fn youtube_items(results: Vec<YoutubeResult>) -> Vec<Item> {
+    results
+        .into_iter()
+        .map(|yt_res| {
+            Item::Track(Track {
+                duration: yt_res.duration,
+                ..Track::new(yt_res.title, yt_res.channel, Uri::Youtube(yt_res.href))
+            })
+        })
+        .collect()
+}
+
 impl App {
     async fn handle_command(&mut self) -> Result<(), Error> {
         match Command::from(self.input.drain(..).collect::<String>()) {
             Command::Unknown => {}
-            Command::Search(query) => {
-                self.results.0.items = self
+            Command::Search(search_type, query) => {
+                let (title, items, next) =
+                    search_items(self.client.search(&query, search_type, 0).await?);
+                if items.is_empty() {
+                    return Err(Error::Other(String::from("Search returned no results.")));
+                }
+                self.set_results(title, items);
+                self.view = View::SpotifySearch {
+                    query,
+                    search_type,
+                    next,
+                };
+            }
+            Command::YTSearch(query) => {
+                let page = YoutubeClient::search(query)?;
+                self.set_results("Tracks", youtube_items(page.results));
+                self.view = View::YoutubeSearch {
+                    continuation: page.continuation,
+                };
+            }
+            Command::Find(query) => {
+                let youtube_query = query.clone();
+                let (spotify, youtube) = futures::join!(
+                    self.client.search(&query, SearchType::Track, 0),
+                    // Searching YouTube blocks, so it runs on its own thread meanwhile
+                    tokio::task::spawn_blocking(move || {
+                        YoutubeClient::search(youtube_query).map_err(|err| err.to_string())
+                    }),
+                );
+                let spotify = spotify.map(|result| search_items(result).1);
+                let youtube = match youtube {
+                    Ok(Ok(page)) => Ok(youtube_items(page.results)),
+                    Ok(Err(err)) => Err(Error::Other(err)),
+                    Err(err) => Err(Error::Other(err.to_string())),
+                };
+
+                let (spotify, youtube) = match (spotify, youtube) {
+                    (Ok(spotify), Ok(youtube)) => (spotify, youtube),
+                    (Ok(spotify), Err(err)) => {
+                        self.status = format!("YouTube search failed: {}", err);
+                        (spotify, vec![])
+                    }
+                    (Err(err), Ok(youtube)) => {
+                        self.status = format!("Spotify search failed: {}", err);
+                        (vec![], youtube)
+                    }
+                    (Err(err), Err(_)) => return Err(err),
+                };
+
+                // Alternate between the providers, so the best matches of both come first
+                let mut items = vec![];
+                let (mut spotify, mut youtube) = (spotify.into_iter(), youtube.into_iter());
+                loop {
+                    match (spotify.next(), youtube.next()) {
+                        (None, None) => break,
+                        (spotify, youtube) => items.extend(spotify.into_iter().chain(youtube)),
+                    }
+                }
+                self.set_results("Tracks", items);
+            }
+            Command::Recent => {
+                let tracks = self
                     .client
-                    .search(query)
+                    .recently_played()
                     .await?
                     .into_iter()
-                    .map(|track| -> Option<Track> {
-                        Some(Track::new(
-                            track.name,
-                            track.artists.first()?.name.clone(),
-                            Uri::Spotify(track.id.unwrap().uri()),
-                        ))
+                    .filter_map(Track::from_spotify)
+                    .map(Item::Track)
+                    .collect();
+                self.set_results("Recently played", tracks);
+            }
+            Command::Top(search_type, time_range) => {
+                let period = match time_range {
+                    TimeRange::ShortTerm => "last 4 weeks",
+                    TimeRange::MediumTerm => "last 6 months",
+                    TimeRange::LongTerm => "all time",
+                };
+                if search_type == SearchType::Artist {
+                    let artists = self
+                        .client
+                        .top_artists(time_range)
+                        .await?
+                        .into_iter()
+                        .map(|artist| Item::Artist(artist.into()))
+                        .collect();
+                    self.set_results(&format!("Top artists ({})", period), artists);
+                } else {
+                    let tracks = self
+                        .client
+                        .top_tracks(time_range)
+                        .await?
+                        .into_iter()
+                        .filter_map(Track::from_spotify)
+                        .map(Item::Track)
+                        .collect();
+                    self.set_results(&format!("Top tracks ({})", period), tracks);
+                }
+            }
+            Command::History => {
+                let tracks = self
+                    .listening
+                    .plays()
+                    .map(|play| Item::Track(play.track.clone()))
+                    .collect();
+                self.set_results("History", tracks);
+            }
+            Command::Stats(period) => self.show_stats(period),
+            Command::Open(link) => self.open_link(link).await?,
+            Command::YTPlaylist(url) => self.open_youtube_playlist(&url),
+            Command::Play(query) => {
+                let track = match self.client.search(&query, SearchType::Track, 0).await? {
+                    SearchResult::Tracks(page) => page.items.into_iter().next(),
+                    _ => None,
+                }
+                .and_then(Track::from_spotify)
+                .ok_or_else(|| Error::Other(String::from("Search returned no results.")))?;
+                self.play_track(track).await?;
+            }
+
+            Command::Pause => self.toggle_pause()?,
+
+            Command::Volume(volume) => self.player.set_volume(volume),
+
+            Command::Autoplay => {
+                self.config.autoplay = !self.config.autoplay;
+                self.config.save()?;
+            }
+
+            Command::CacheInfo => {
+                let info = self.player.youtube.cache.info()?;
+                self.status = format!(
+                    "{} tracks, {:.1} of {} MB in {}",
+                    info.files,
+                    info.size as f64 / (1024.0 * 1024.0),
+                    info.limit / (1024 * 1024),
+                    info.dir.display()
+                );
+            }
+
+            Command::CacheClear => {
+                self.player.youtube.clear_cache()?;
+                self.status = String::from("Cache cleared");
+            }
+
+            Command::Scan(dir) => {
+                let dir = match (dir.strip_prefix("~/"), dirs::home_dir()) {
+                    (Some(rest), Some(home)) => home.join(rest),
+                    _ => PathBuf::from(dir),
+                };
+                self.status = format!("Scanning {}...", dir.display());
+                let tx = self.tx.clone();
+                tokio::task::spawn_blocking(move || {
+                    tx.send(match LocalLibrary::scan(&dir) {
+                        Ok(scan) => Event::LocalScanned(scan),
+                        Err(err) => Event::LocalScanFailed(dir, err.to_string()),
                     })
-                    .collect::<Option<Vec<Track>>>()
-                    .ok_or_else(|| Error::Other(String::from("Search returned no results.")))?;
-                self.results.1 = Platform::Spotify;
+                    .unwrap()
+                });
             }
-            Command::YTSearch(query) => {
-                self.results.0.items = YoutubeClient::search(query)?
+
+            Command::Local(query) => {
+                let tracks = self
+                    .local
+                    .search(query.trim())
                     .into_iter()
-                    .map(|yt_res| {
-                        Track::new(yt_res.title, String::new(), Uri::Youtube(yt_res.href))
+                    .map(|track| {
+                        Item::Track(Track {
+                            duration: Some(track.duration),
+                            ..Track::new(
+                                track.title.clone(),
+                                track.artist.clone(),
+                                Uri::Local(track.path.clone()),
+                            )
+                        })
                     })
                     .collect();
-                self.results.1 = Platform::Youtube;
+                self.set_results("Local files", tracks);
             }
-            Command::Play(query) => {
-                self.player
-                    .play(Uri::Spotify(
-                        self.client
-                            .search(query)
-                            .await?
-                            .clone()
-                            .first()
-                            .expect("No results")
-                            .id
-                            .clone()
-                            .unwrap()
-                            .to_string()
-                            .clone(),
-                    ))
-                    .await;
-                self.set_playback_state(false)?;
-                // TODO: Add to self.current
-            }
-
-            Command::Pause => {
-                if self.paused {
-                    self.player.resume();
-                    self.set_playback_state(false)?;
-                } else {
-                    self.player.pause();
-                    self.set_playback_state(true)?;
+
+            Command::Like => self.set_saved(self.current.clone(), true).await?,
+            Command::Unlike => self.set_saved(self.current.clone(), false).await?,
+
+            Command::Playlists => self.show_playlists().await?,
+
+            Command::PlaylistNew(name) => {
+                let playlist = self.client.create_playlist(&name).await?;
+                self.status = format!("Created {}", playlist.name);
+                self.refresh_playlist(&playlist.id.uri()).await?;
+            }
+
+            Command::PlaylistAdd(name) => {
+                let playlist = self.find_playlist(&name).await?;
+                if let Some(Item::Track(Track {
+                    uri: Uri::Spotify(uri),
+                    name,
+                    ..
+                })) = self.results.get_selection().cloned()
+                {
+                    self.client.add_to_playlist(&playlist.uri, &[uri]).await?;
+                    self.status = format!("Added {} to {}", name, playlist.name);
+                    self.refresh_playlist(&playlist.uri).await?;
+                }
+            }
+
+            Command::PlaylistAddQueue(name) => {
+                let playlist = self.find_playlist(&name).await?;
+                let tracks = self
+                    .queue
+                    .iter()
+                    .filter_map(|track| match &track.uri {
+                        Uri::Spotify(uri) => Some(uri.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<String>>();
+                self.client.add_to_playlist(&playlist.uri, &tracks).await?;
+                self.status = format!("Added {} tracks to {}", tracks.len(), playlist.name);
+                self.refresh_playlist(&playlist.uri).await?;
+            }
+
+            Command::PlaylistRemove => {
+                if let (
+                    View::Playlist(playlist, positions),
+                    Some(selected),
+                    Some(Item::Track(Track {
+                        uri: Uri::Spotify(uri),
+                        name,
+                        ..
+                    })),
+                ) = (
+                    &self.view,
+                    self.results.state.selected(),
+                    self.results.get_selection().cloned(),
+                ) {
+                    // Only the selected row goes, other copies of the track stay
+                    let position = *positions.get(selected).ok_or_else(|| {
+                        Error::Other(format!("No track at position {}", selected))
+                    })?;
+                    let (playlist, snapshot_id) =
+                        (playlist.uri.clone(), playlist.snapshot_id.clone());
+                    self.client
+                        .remove_from_playlist(&playlist, &uri, position, &snapshot_id)
+                        .await?;
+                    self.status = format!("Removed {}", name);
+                    self.refresh_playlist(&playlist).await?;
+                }
+            }
+
+            Command::PlaylistRename(name) => {
+                if let View::Playlist(playlist, _) = &mut self.view {
+                    self.client.rename_playlist(&playlist.uri, &name).await?;
+                    playlist.name = name;
+                    let playlist = playlist.uri.clone();
+                    self.refresh_playlist(&playlist).await?;
+                }
+            }
+
+            Command::PlaylistMove(to) => {
+                if let (View::Playlist(playlist, positions), Some(from)) =
+                    (&self.view, self.results.state.selected())
+                {
+                    let (from_position, to_position) =
+                        match (positions.get(from), positions.get(to)) {
+                            (Some(from), Some(to)) => (*from, *to),
+                            _ => return Err(Error::Other(format!("No track at position {}", to))),
+                        };
+                    let playlist = playlist.uri.clone();
+                    // The track is inserted before the one at `to` when moving up,
+                    // and after it when moving down
+                    let before = if to > from {
+                        to_position + 1
+                    } else {
+                        to_position
+                    };
+                    self.client
+                        .move_in_playlist(&playlist, from_position, before)
+                        .await?;
+                    self.refresh_playlist(&playlist).await?;
+                    self.results.state.select(Some(to));
                 }
             }
 
             Command::Library => {
-                self.results.0.items = self
-                    .client
-                    .clone()
-                    .get_library()
-                    .await
+                self.sync_library();
+                if self.library.synced {
+                    self.show_library();
+                } else {
+                    // Until the first sync is done, page through the library online
+                    self.set_results("Library", vec![]);
+                    self.view = View::Library {
+                        loaded: 0,
+                        total: None,
+                    };
+                    self.load_library().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_results(&mut self, title: &str, items: Vec<Item>) {
+        self.results.set_items(items);
+        self.results_title = String::from(title);
+        self.view = View::Search;
+    }
+
+    async fn show_playlists(&mut self) -> NeoResult<()> {
+        let playlists = self
+            .client
+            .playlists()
+            .await?
+            .into_iter()
+            .map(|playlist| Item::Playlist(Playlist::from(playlist)))
+            .collect();
+        self.set_results("Playlists", playlists);
+        self.view = View::Playlists;
+        Ok(())
+    }
+
+    /// Fetches the next page of whatever the results list is showing once the
+    /// selection gets close to the end of it
+    async fn load_more(&mut self) -> NeoResult<()> {
+        match self.results.state.selected() {
+            Some(selected) if selected + LOAD_AHEAD >= self.results.items.len() => {}
+            _ => return Ok(()),
+        }
+        match self.view {
+            View::Library { .. } => self.load_library().await,
+            View::SpotifySearch { .. } => self.load_spotify_search().await,
+            View::YoutubeSearch { .. } => self.load_youtube_search(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Appends the next page of Spotify search results
+    async fn load_spotify_search(&mut self) -> NeoResult<()> {
+        let (query, search_type, offset) = match &self.view {
+            View::SpotifySearch {
+                query,
+                search_type,
+                next: Some(next),
+            } => (query.clone(), *search_type, *next),
+            _ => return Ok(()),
+        };
+        let (_, items, next) = search_items(self.client.search(&query, search_type, offset).await?);
+        self.results.items.extend(items);
+        self.view = View::SpotifySearch {
+            query,
+            search_type,
+            next,
+        };
+        Ok(())
+    }
+
+    /// Appends the next page of YouTube search results
+    fn load_youtube_search(&mut self) -> NeoResult<()> {
+        let continuation = match &mut self.view {
+            View::YoutubeSearch { continuation } => continuation.take(),
+            _ => None,
+        };
+        if let Some(continuation) = continuation {
+            let page = match YoutubeClient::search_more(&continuation) {
+                Ok(page) => page,
+                Err(err) => {
+                    // Keep the token so scrolling tries again
+                    self.view = View::YoutubeSearch {
+                        continuation: Some(continuation),
+                    };
+                    return Err(err);
+                }
+            };
+            self.results.items.extend(youtube_items(page.results));
+            self.view = View::YoutubeSearch {
+                continuation: page.continuation,
+            };
+        }
+        Ok(())
+    }
+
+    /// Shows the saved tracks as of the last sync, keeping the selection if the
+    /// library was already shown
+    fn show_library(&mut self) {
+        let selected = match self.view {
+            View::Library { .. } => self.results.state.selected(),
+            _ => None,
+        };
+        let tracks = self
+            .library
+            .tracks
+            .iter()
+            .map(|track| {
+                Item::Track(Track {
+                    duration: Some(track.duration),
+                    ..Track::new(
+                        track.name.clone(),
+                        track.artist.clone(),
+                        Uri::Spotify(track.uri.clone()),
+                    )
+                })
+            })
+            .collect::<Vec<Item>>();
+        let total = tracks.len() as u32;
+        self.set_results(&format!("Library ({} tracks)", total), tracks);
+        if let (Some(selected), false) = (selected, self.results.items.is_empty()) {
+            self.results
+                .state
+                .select(Some(selected.min(self.results.items.len() - 1)));
+        }
+        self.view = View::Library {
+            loaded: total,
+            total: Some(total),
+        };
+    }
+
+    /// Starts fetching the tracks saved and unsaved since the last sync, or
+    /// once the running sync is done
+    fn sync_library(&mut self) {
+        if self.syncing {
+            self.resync = true;
+            return;
+        }
+        self.syncing = true;
+        let library = self.library.clone();
+        let client = self.client.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            tx.send(match library.sync(&client).await {
+                Ok(library) => Event::LibrarySynced(library),
+                Err(err) => Event::LibrarySyncFailed(err.to_string()),
+            })
+            .unwrap()
+        });
+    }
+
+    fn is_saved(&self, track: &Track) -> bool {
+        match &track.uri {
+            Uri::Spotify(uri) => self.saved.get(uri).copied().unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Marks every track in the library as saved, so they don't need checking.
+    /// Tracks marked saved that aren't in it anymore are checked again
+    fn remember_library(&mut self) {
+        let library = self
+            .library
+            .tracks
+            .iter()
+            .map(|track| track.uri.as_str())
+            .collect::<HashSet<&str>>();
+        self.saved
+            .retain(|uri, saved| !*saved || library.contains(uri.as_str()));
+        for track in &self.library.tracks {
+            self.saved.insert(track.uri.clone(), true);
+        }
+    }
+
+    /// Checks whether the listed, queued and current Spotify tracks are saved,
+    /// for the ones that weren't checked yet
+    async fn check_saved(&mut self) -> NeoResult<()> {
+        let unchecked = self
+            .results
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Track(track) => Some(track),
+                _ => None,
+            })
+            .chain(self.queue.iter())
+            .chain(self.current.iter())
+            .filter_map(|track| match &track.uri {
+                Uri::Spotify(uri) if !self.saved.contains_key(uri) => Some(uri.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        if unchecked.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.client.contains_tracks(&unchecked).await;
+        // Tracks that couldn't be checked are shown as not saved rather than
+        // being checked again on every tick
+        let saved = result.as_ref().cloned().unwrap_or_default();
+        for (i, uri) in unchecked.into_iter().enumerate() {
+            self.saved
+                .insert(uri, saved.get(i).copied().unwrap_or(false));
+        }
+        result.map(|_| ())
+    }
+
+    /// Saves or unsaves a Spotify track, and updates the library to match
+    async fn set_saved(&mut self, track: Option<Track>, saved: bool) -> NeoResult<()> {
+        let (uri, name) = match track {
+            Some(Track {
+                uri: Uri::Spotify(uri),
+                name,
+                ..
+            }) => (uri, name),
+            Some(_) => {
+                return Err(Error::Other(String::from(
+                    "Only Spotify tracks can be saved",
+                )))
+            }
+            None => return Ok(()),
+        };
+        if saved {
+            self.client.save_tracks(&[uri.clone()]).await?;
+            self.status = format!("Saved {}", name);
+        } else {
+            self.client.unsave_tracks(&[uri.clone()]).await?;
+            self.status = format!("Removed {} from the library", name);
+        }
+        self.saved.insert(uri.clone(), saved);
+        if saved {
+            // Only takes a request, as the sync stops at the first track it knows
+            self.sync_library();
+        } else {
+            self.library.remove(&uri);
+            self.library.save()?;
+            if self.syncing {
+                self.unsaved_while_syncing.push(uri);
+            }
+            if let (View::Library { .. }, true) = (&self.view, self.library.synced) {
+                self.show_library();
+            }
+        }
+        Ok(())
+    }
+
+    async fn toggle_saved(&mut self, track: Option<Track>) -> NeoResult<()> {
+        let saved = track.as_ref().map_or(false, |track| self.is_saved(track));
+        self.set_saved(track, !saved).await
+    }
+
+    /// Appends the next page of saved tracks to the library view
+    async fn load_library(&mut self) -> NeoResult<()> {
+        let loaded = match self.view {
+            View::Library { loaded, total } if total.map_or(true, |total| loaded < total) => loaded,
+            _ => return Ok(()),
+        };
+        let (tracks, total) = self.client.get_library(loaded, LIBRARY_PAGE).await?;
+        let loaded = loaded + tracks.len() as u32;
+        // Stop asking for more if the library shrank while it was being loaded
+        let total = if tracks.is_empty() { loaded } else { total };
+
+        self.results.items.extend(
+            tracks
+                .into_iter()
+                .filter_map(|saved| Track::from_spotify(saved.track))
+                .map(Item::Track),
+        );
+        if self.results.state.selected().is_none() && !self.results.items.is_empty() {
+            self.results.state.select(Some(0));
+        }
+        self.results_title = format!("Library ({} of {})", loaded, total);
+        self.view = View::Library {
+            loaded,
+            total: Some(total),
+        };
+        Ok(())
+    }
+
+    /// Finds one of the user's playlists by name, ignoring case
+    async fn find_playlist(&self, name: &str) -> NeoResult<Playlist> {
+        self.client
+            .playlists()
+            .await?
+            .into_iter()
+            .find(|playlist| playlist.name.to_lowercase() == name.to_lowercase())
+            .map(Playlist::from)
+            .ok_or_else(|| Error::Other(format!("No playlist named {}", name)))
+    }
+
+    /// Shows the tracks of a playlist in the results list
+    async fn open_playlist(&mut self, playlist: &Playlist) -> NeoResult<()> {
+        // Fetched before the tracks, as their positions only hold for this version
+        let mut playlist = playlist.clone();
+        playlist.snapshot_id = self.client.playlist(&playlist.uri).await?.snapshot_id;
+
+        let (positions, tracks): (Vec<u32>, Vec<Item>) = self
+            .client
+            .playlist_tracks(&playlist.uri)
+            .await?
+            .into_iter()
+            .filter_map(|(position, track)| {
+                Some((position, Item::Track(Track::from_playable(track)?)))
+            })
+            .unzip();
+        self.set_results(&playlist.name, tracks);
+        self.view = View::Playlist(playlist, positions);
+        Ok(())
+    }
+
+    /// Reloads the results list after a playlist was edited, if it's showing the
+    /// list of playlists or that playlist, keeping the selection where it was
+    async fn refresh_playlist(&mut self, uri: &str) -> NeoResult<()> {
+        let selected = self.results.state.selected();
+        match &self.view {
+            View::Playlists => self.show_playlists().await?,
+            View::Playlist(playlist, _) if playlist.uri == uri => {
+                let playlist = playlist.clone();
+                self.open_playlist(&playlist).await?
+            }
+            _ => return Ok(()),
+        }
+        if let (Some(selected), false) = (selected, self.results.items.is_empty()) {
+            self.results
+                .state
+                .select(Some(selected.min(self.results.items.len() - 1)));
+        }
+        Ok(())
+    }
+
+    /// Shows the tracks of an album in the results list
+    async fn open_album(&mut self, album: &Album) -> NeoResult<()> {
+        let tracks = self
+            .client
+            .album_tracks(&album.uri)
+            .await?
+            .into_iter()
+            .filter_map(Track::from_simplified)
+            .map(Item::Track)
+            .collect();
+        self.set_results(&album.to_string(), tracks);
+        Ok(())
+    }
+
+    /// Shows the top tracks, albums and related artists of an artist in the results list
+    async fn open_artist(&mut self, artist: &Artist) -> NeoResult<()> {
+        let (top_tracks, albums, related) = futures::try_join!(
+            self.client.artist_top_tracks(&artist.uri),
+            self.client.artist_albums(&artist.uri),
+            self.client.related_artists(&artist.uri),
+        )?;
+        let items = top_tracks
+            .into_iter()
+            .filter_map(Track::from_spotify)
+            .map(Item::Track)
+            .chain(
+                albums
                     .into_iter()
-                    .filter_map(|track| {
-                        if let (Some(first_artist), Some(id)) = (track.artists.first(), track.id) {
-                            Some(Track::new(
-                                track.name,
-                                first_artist.name.clone(),
-                                Uri::Spotify(id.uri()),
-                            ))
-                        } else {
-                            None
-                        }
+                    .filter_map(Album::from_spotify)
+                    .map(Item::Album),
+            )
+            .chain(
+                related
+                    .into_iter()
+                    .map(|artist| Item::Artist(artist.into())),
+            )
+            .collect();
+        self.set_results(&artist.name, items);
+        Ok(())
+    }
+
+    /// Shows the latest episodes of a podcast in the results list
+    async fn open_show(&mut self, show: &Show) -> NeoResult<()> {
+        let episodes = self
+            .client
+            .show_episodes(&show.uri)
+            .await?
+            .into_iter()
+            .map(|episode| {
+                Item::Track(Track::new(
+                    episode.name,
+                    show.name.clone(),
+                    Uri::Episode(episode.id.uri()),
+                ))
+            })
+            .collect();
+        self.set_results(&show.name, episodes);
+        Ok(())
+    }
+
+    /// Opens an album, artist, playlist or podcast in the results list, remembering the
+    /// current list so `go_back` can return to it
+    async fn open(&mut self, item: Item) -> NeoResult<()> {
+        let page = self.page();
+        match item {
+            Item::Playlist(playlist) => self.open_playlist(&playlist).await?,
+            Item::Album(album) => self.open_album(&album).await?,
+            Item::Artist(artist) => self.open_artist(&artist).await?,
+            Item::Show(show) => self.open_show(&show).await?,
+            Item::Track(_) | Item::Stat(_) => return Ok(()),
+        }
+        self.back.push(page);
+        Ok(())
+    }
+
+    /// The results list as it is now, to be pushed to `back` once something
+    /// opened from it has loaded
+    fn page(&self) -> Page {
+        Page {
+            title: self.results_title.clone(),
+            items: self.results.items.clone(),
+            view: self.view.clone(),
+            selected: self.results.state.selected(),
+        }
+    }
+
+    /// Shows the videos of a YouTube playlist, or the uploads of a channel, in the results list
+    /// Lists the videos of a YouTube playlist or channel in the background, as
+    /// yt-dlp can take a while on large ones. They're shown with
+    /// `Event::YoutubePlaylistLoaded`
+    fn open_youtube_playlist(&mut self, url: &str) {
+        self.status = format!("Loading {}...", url);
+        let url = String::from(url);
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            tx.send(match YoutubeClient::playlist(&url) {
+                Ok(playlist) => Event::YoutubePlaylistLoaded(playlist),
+                Err(err) => Event::YoutubePlaylistFailed(err.to_string()),
+            })
+            .unwrap()
+        });
+    }
+
+    /// Plays the track, episode or video a link points to, or opens the album,
+    /// artist, playlist or podcast in the results list
+    async fn open_link(&mut self, link: Link) -> NeoResult<()> {
+        match link {
+            Link::Spotify(uri) if uri.starts_with("spotify:track:") => {
+                let track = Track::from_spotify(self.client.track(&uri).await?)
+                    .ok_or_else(|| Error::Other(format!("Can't play {}", uri)))?;
+                self.play_track(track).await
+            }
+            Link::Spotify(uri) if uri.starts_with("spotify:episode:") => {
+                let episode = self.client.episode(&uri).await?;
+                self.play_track(Track::new(
+                    episode.name,
+                    episode.show.name,
+                    Uri::Episode(uri),
+                ))
+                .await
+            }
+            Link::Spotify(uri) if uri.starts_with("spotify:album:") => {
+                let album = self.client.album(&uri).await?;
+                let artist = album.artists.first();
+                self.open(Item::Album(Album {
+                    artist: artist.map(|artist| artist.name.clone()).unwrap_or_default(),
+                    artist_uri: artist.and_then(|artist| Some(artist.id.as_ref()?.uri())),
+                    name: album.name,
+                    uri,
+                }))
+                .await
+            }
+            Link::Spotify(uri) if uri.starts_with("spotify:artist:") => {
+                let artist = self.client.artist(&uri).await?;
+                self.open(Item::Artist(artist.into())).await
+            }
+            Link::Spotify(uri) if uri.starts_with("spotify:playlist:") => {
+                let playlist = self.client.playlist(&uri).await?;
+                self.open(Item::Playlist(Playlist {
+                    name: playlist.name,
+                    owner: playlist
+                        .owner
+                        .display_name
+                        .unwrap_or_else(|| String::from(playlist.owner.id.id())),
+                    tracks: playlist.tracks.total,
+                    uri,
+                    snapshot_id: playlist.snapshot_id,
+                }))
+                .await
+            }
+            Link::Spotify(uri) => {
+                let show = self.client.show(&uri).await?;
+                self.open(Item::Show(Show {
+                    name: show.name,
+                    publisher: show.publisher,
+                    uri,
+                }))
+                .await
+            }
+            Link::YoutubeVideo(video_id) => {
+                // The title only shows what's playing, so the ID will do if it can't be found
+                let (title, channel) = YoutubeClient::video_info(&video_id)
+                    .unwrap_or_else(|_| (video_id.clone(), String::new()));
+                self.play_track(Track::new(title, channel, Uri::Youtube(video_id)))
+                    .await
+            }
+            Link::YoutubePlaylist(playlist_id) => {
+                self.open_youtube_playlist(&format!(
+                    "https://www.youtube.com/playlist?list={}",
+                    playlist_id
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns to the list the current one was opened from
+    fn go_back(&mut self) {
+        if let Some(page) = self.back.pop() {
+            self.set_results(&page.title, page.items);
+            self.view = page.view;
+            self.results.state.select(page.selected);
+        }
+    }
+
+    /// Opens the album of the selected track
+    async fn go_to_album(&mut self) -> NeoResult<()> {
+        let album = match self.results.get_selection() {
+            Some(Item::Track(Track {
+                uri: Uri::Spotify(uri),
+                ..
+            })) => Album::from_spotify(self.client.track(uri).await?.album),
+            Some(Item::Album(album)) => Some(album.clone()),
+            _ => None,
+        };
+        match album {
+            Some(album) => self.open(Item::Album(album)).await,
+            None => Err(Error::Other(String::from(
+                "Only Spotify tracks have an album",
+            ))),
+        }
+    }
+
+    /// Opens the artist of the selected track or album
+    async fn go_to_artist(&mut self) -> NeoResult<()> {
+        let artist = match self.results.get_selection() {
+            Some(Item::Track(Track {
+                uri: Uri::Spotify(uri),
+                ..
+            })) => self
+                .client
+                .track(uri)
+                .await?
+                .artists
+                .into_iter()
+                .next()
+                .and_then(|artist| {
+                    Some(Artist {
+                        uri: artist.id?.uri(),
+                        name: artist.name,
                     })
-                    .collect();
-                self.results.1 = Platform::Spotify;
+                }),
+            Some(Item::Album(album)) => album.artist_uri.clone().map(|uri| Artist {
+                name: album.artist.clone(),
+                uri,
+            }),
+            Some(Item::Artist(artist)) => Some(artist.clone()),
+            _ => None,
+        };
+        match artist {
+            Some(artist) => self.open(Item::Artist(artist)).await,
+            None => Err(Error::Other(String::from(
+                "Only Spotify tracks and albums have an artist",
+            ))),
+        }
+    }
+
+    /// Opens the selected album, artist, playlist or podcast, or plays the selected track
+    async fn select(&mut self) -> NeoResult<()> {
+        match self.results.get_selection().cloned() {
+            Some(Item::Track(track)) => self.play_track(track).await,
+            Some(item) => self.open(item).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the selected track to the queue, or every track of the selected
+    /// playlist or album, or the top tracks of the selected artist
+    async fn queue_selection(&mut self) -> NeoResult<()> {
+        match self.results.get_selection().cloned() {
+            Some(Item::Track(track)) => self.queue.push(track),
+            Some(Item::Playlist(playlist)) => {
+                let tracks = self.client.playlist_tracks(&playlist.uri).await?;
+                self.queue.extend(
+                    tracks
+                        .into_iter()
+                        .filter_map(|(_, track)| Track::from_playable(track)),
+                );
+                self.status = format!("Queued {}", playlist.name);
+            }
+            Some(Item::Album(album)) => {
+                let tracks = self.client.album_tracks(&album.uri).await?;
+                self.queue
+                    .extend(tracks.into_iter().filter_map(Track::from_simplified));
+                self.status = format!("Queued {}", album.name);
+            }
+            Some(Item::Artist(artist)) => {
+                let tracks = self.client.artist_top_tracks(&artist.uri).await?;
+                self.queue
+                    .extend(tracks.into_iter().filter_map(Track::from_spotify));
+                self.status = format!("Queued the top tracks of {}", artist.name);
+            }
+            Some(Item::Show(show)) => {
+                self.status = format!("Open {} to queue its episodes", show.name);
+            }
+            Some(Item::Stat(_)) | None => {}
+        }
+        Ok(())
+    }
+
+    /// The tracks in the results list, from the one at `start` onwards
+    fn result_tracks(&self, start: usize) -> Vec<Track> {
+        self.results
+            .items
+            .iter()
+            .skip(start)
+            .filter_map(|item| match item {
+                Item::Track(track) => Some(track.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Plays the selected track and replaces the queue with the tracks listed after it
+    async fn play_from_selection(&mut self) -> NeoResult<()> {
+        if let Some(selected) = self.results.state.selected() {
+            let mut tracks = self.result_tracks(selected).into_iter();
+            if let Some(first) = tracks.next() {
+                self.queue = tracks.collect();
+                self.play_track(first).await?;
             }
         }
         Ok(())
     }
 
+    /// Plays a track, moving the current one to the history
+    async fn play_track(&mut self, track: Track) -> NeoResult<()> {
+        self.save_episode_position()?;
+        self.record_play(true)?;
+        let start = match &track.uri {
+            Uri::Episode(uri) => self.episodes.get(uri).unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+        self.player.play(track.uri.clone(), start).await?;
+        if let Uri::Youtube(_) | Uri::Local(_) = track.uri {
+            self.np = track.name.clone()
+        }
+        // Spotify tracks and episodes get the rest of their metadata with `Event::UpdateNP`
+        self.metadata = Some(Metadata {
+            title: track.name.clone(),
+            artist: track.artist.clone(),
+            album: match &track.uri {
+                Uri::Local(path) => self.local.get(path).map(|local| local.album.clone()),
+                _ => None,
+            },
+            cover_url: match &track.uri {
+                Uri::Youtube(video_id) => {
+                    Some(format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", video_id))
+                }
+                _ => None,
+            },
+            duration: track.duration,
+        });
+        if let Some(current) = self.current.take() {
+            self.history.insert(0, current);
+        }
+        self.current = Some(track);
+        self.played_at = Some(ListeningHistory::now());
+        self.set_playback_state(false)
+    }
+
+    /// Writes the current track to the listening history, unless it already was
+    /// or never started, like a YouTube video that failed to download.
+    /// `skipped` means it was stopped before its end
+    fn record_play(&mut self, skipped: bool) -> NeoResult<()> {
+        let started = self.player.has_started();
+        if let (Some(track), Some(played_at), true) =
+            (&self.current, self.played_at.take(), started)
+        {
+            self.listening.record(Play {
+                track: track.clone(),
+                played_at,
+                listened: self.player.played(),
+                skipped,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Lists the most played tracks and artists, and the time listened in the
+    /// last days or weeks
+    fn show_stats(&mut self, period: Period) {
+        let mut lines = vec![String::from("Most played tracks")];
+        lines.extend(self.listening.top_tracks(10).into_iter().map(|plays| {
+            format!(
+                "  {} plays, {}  {}",
+                plays.count,
+                format_duration(plays.listened),
+                plays.item
+            )
+        }));
+        lines.push(String::from("Most played artists"));
+        lines.extend(self.listening.top_artists(10).into_iter().map(|plays| {
+            format!(
+                "  {} plays, {}  {}",
+                plays.count,
+                format_duration(plays.listened),
+                plays.item
+            )
+        }));
+        lines.push(String::from(match period {
+            Period::Day => "Time listened by day",
+            Period::Week => "Time listened by week",
+        }));
+        lines.extend(
+            self.listening
+                .listening_time(period)
+                .into_iter()
+                .map(|(label, listened)| format!("  {}  {}", label, format_duration(listened))),
+        );
+        self.set_results("Stats", lines.into_iter().map(Item::Stat).collect());
+    }
+
+    /// Plays the next track in the queue following the repeat and shuffle modes.
+    /// `skipped` means the user asked for it, rather than the current track ending
+    async fn play_next(&mut self, skipped: bool) -> NeoResult<()> {
+        if !skipped {
+            self.record_play(false)?;
+        }
+        // A track that never got to play, like a YouTube video that failed to
+        // download, would otherwise be tried again forever
+        let started = self.player.has_started();
+        if !skipped && started && self.repeat == Repeat::One {
+            if let Some(current) = self.current.take() {
+                return self.play_track(current).await;
+            }
+        }
+        if started && self.repeat == Repeat::All {
+            if let Some(current) = self.current.clone() {
+                self.queue.push(current);
+            }
+        }
+
+        if self.queue.is_empty() && self.config.autoplay {
+            if let Err(err) = self.autoplay().await {
+                self.status = format!("Autoplay failed: {}", err);
+            }
+        }
+
+        if self.queue.is_empty() {
+            if !skipped {
+                if let Some(current) = self.current.take() {
+                    self.history.insert(0, current);
+                }
+                if let Some(controls) = self.os_media_controls.borrow_mut() {
+                    controls.set_playback(MediaPlayback::Stopped)?
+                }
+            }
+            return Ok(());
+        }
+
+        let next = self.queue.remove(if self.shuffle {
+            rand::thread_rng().gen_range(0..self.queue.len())
+        } else {
+            0
+        });
+        self.play_track(next).await
+    }
+
+    /// Queues Spotify recommendations seeded by the last played tracks, leaving
+    /// out anything played recently
+    async fn autoplay(&mut self) -> NeoResult<()> {
+        let recent = self
+            .current
+            .iter()
+            .chain(self.history.iter().take(AUTOPLAY_HISTORY))
+            .filter_map(|track| match &track.uri {
+                Uri::Spotify(uri) => Some(uri.clone()),
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        if recent.is_empty() {
+            return Ok(());
+        }
+
+        let recommendations = self
+            .client
+            .recommendations(&recent[..recent.len().min(5)])
+            .await?;
+        let recent = recent.into_iter().collect::<HashSet<String>>();
+        self.queue.extend(
+            recommendations
+                .into_iter()
+                .filter_map(Track::from_simplified)
+                .filter(|track| match &track.uri {
+                    Uri::Spotify(uri) => !recent.contains(uri),
+                    _ => true,
+                }),
+        );
+        Ok(())
+    }
+
+    /// Plays the last track in the history, putting the current one back in the queue
+    async fn play_previous(&mut self) -> NeoResult<()> {
+        if !self.history.is_empty() {
+            self.record_play(true)?;
+            let previous = self.history.remove(0);
+            if let Some(current) = self.current.take() {
+                self.queue.insert(0, current);
+            }
+            self.play_track(previous).await?;
+        }
+        Ok(())
+    }
+
+    fn toggle_pause(&mut self) -> NeoResult<()> {
+        if self.paused {
+            self.player.resume();
+            self.set_playback_state(false)
+        } else {
+            self.player.pause();
+            self.set_playback_state(true)?;
+            self.save_episode_position()
+        }
+    }
+
+    /// Remembers where the current podcast episode is, so it resumes there when played again
+    fn save_episode_position(&mut self) -> NeoResult<()> {
+        if let Some(Track {
+            uri: Uri::Episode(uri),
+            ..
+        }) = &self.current
+        {
+            self.episodes
+                .set(uri, self.player.position(), self.player.duration())?;
+            self.episode_saved = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Pauses and rewinds the current track
+    fn stop(&mut self) -> NeoResult<()> {
+        self.record_play(true)?;
+        self.player.pause();
+        self.player.seek(Duration::ZERO)?;
+        self.paused = true;
+        if let Some(controls) = self.os_media_controls.borrow_mut() {
+            controls.set_playback(MediaPlayback::Stopped)?
+        };
+        Ok(())
+    }
+
+    /// Opens a URI or link sent through MPRIS
+    async fn open_uri(&mut self, uri: String) -> NeoResult<()> {
+        match Link::parse(&uri) {
+            Some(link) => self.open_link(link).await,
+            None => {
+                self.status = format!("Can't open {}", uri);
+                Ok(())
+            }
+        }
+    }
+
     fn set_playback_state(&mut self, paused: bool) -> NeoResult<()> {
         self.paused = paused;
+        let progress = Some(MediaPosition(self.player.position()));
         if let Some(controls) = self.os_media_controls.borrow_mut() {
             controls.set_playback(if paused {
-                MediaPlayback::Paused { progress: None }
+                MediaPlayback::Paused { progress }
             } else {
-                MediaPlayback::Playing { progress: None }
+                MediaPlayback::Playing { progress }
             })?
         };
         Ok(())
@@ -226,6 +1902,10 @@ impl App {
 async fn main() -> Result<(), Error> {
     let player = SpotifyPlayer::new().await?;
     let (_stream, handle) = rodio::OutputStream::try_default().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    let config = Config::load();
+    let cache = Cache::new(config.cache_size)?;
 
     let mut app = App {
         client: SpotifyClient::new({
@@ -237,18 +1917,39 @@ async fn main() -> Result<(), Error> {
             }
         })
         .await,
-        player: Player::new(handle).await?,
+        player: Player::new(handle, tx.clone(), cache).await?,
         input: String::new(),
         input_mode: InputMode::Normal,
-        results: (StatefulList::new(), Platform::Spotify),
+        results: StatefulList::new(),
+        results_title: String::from("Tracks"),
+        view: View::Search,
+        back: vec![],
         queue: vec![],
         history: vec![],
         current: None,
         np: String::new(),
+        download: None,
         toggle_queue: true,
         paused: true,
+        repeat: Repeat::Off,
+        shuffle: false,
         // TODO: Find a way to make this async, or initialize the app without it at first (it takes considerable time to load and delays the app start)
         os_media_controls: None,
+        config,
+        local: LocalLibrary::load(),
+        library: Library::load(),
+        syncing: false,
+        resync: false,
+        unsaved_while_syncing: vec![],
+        saved: HashMap::new(),
+        episodes: EpisodePositions::load(),
+        episode_saved: Instant::now(),
+        listening: ListeningHistory::load(),
+        played_at: None,
+        tx: tx.clone(),
+        metadata: None,
+        sent_metadata: None,
+        status: String::new(),
     };
 
     // Terminal initialization
@@ -257,7 +1958,15 @@ async fn main() -> Result<(), Error> {
     let mut terminal = Terminal::new(backend)?;
 
     // Setup event handlers
-    let events = Events::new(app.player.spotify.get_event_channel());
+    let events = Events::new(tx, rx, app.player.spotify.get_event_channel());
+
+    app.player.set_volume(app.config.volume);
+
+    app.remember_library();
+    if app.library.synced {
+        app.show_library();
+    }
+    app.sync_library();
 
     if let Ok(controls) = MediaControls::new(PlatformConfig {
         dbus_name: "neoplayer",
@@ -283,19 +1992,34 @@ async fn main() -> Result<(), Error> {
     let tx_clone = events.tx.clone();
 
     if let Some(controls) = app.os_media_controls.borrow_mut() {
+        // souvlaki 0.5 has no events for the MPRIS `LoopStatus` and `Shuffle`
+        // properties, so repeat and shuffle can only be changed with `r` and `s`
         controls.attach(move |event: MediaControlEvent| match event {
             MediaControlEvent::Play => tx_clone.send(Event::PleaseResume).unwrap(),
             MediaControlEvent::Pause => tx_clone.send(Event::PleasePause).unwrap(),
-            MediaControlEvent::Toggle => todo!(),
-            MediaControlEvent::Next => todo!(),
-            MediaControlEvent::Previous => todo!(),
-            MediaControlEvent::Stop => todo!(),
-            MediaControlEvent::Seek(_) => todo!(),
-            MediaControlEvent::SeekBy(_, _) => todo!(),
-            MediaControlEvent::SetPosition(_) => todo!(),
-            MediaControlEvent::OpenUri(_) => todo!(),
-            MediaControlEvent::Raise => todo!(),
-            MediaControlEvent::Quit => todo!(),
+            MediaControlEvent::Toggle => tx_clone.send(Event::PleaseToggle).unwrap(),
+            MediaControlEvent::Next => tx_clone.send(Event::PleaseNext).unwrap(),
+            MediaControlEvent::Previous => tx_clone.send(Event::PleasePrevious).unwrap(),
+            MediaControlEvent::Stop => tx_clone.send(Event::PleaseStop).unwrap(),
+            MediaControlEvent::Seek(direction) => tx_clone
+                .send(Event::PleaseSeekBy(match direction {
+                    SeekDirection::Forward => SEEK_STEP,
+                    SeekDirection::Backward => -SEEK_STEP,
+                }))
+                .unwrap(),
+            MediaControlEvent::SeekBy(direction, offset) => tx_clone
+                .send(Event::PleaseSeekBy(match direction {
+                    SeekDirection::Forward => offset.as_millis() as i64,
+                    SeekDirection::Backward => -(offset.as_millis() as i64),
+                }))
+                .unwrap(),
+            MediaControlEvent::SetPosition(MediaPosition(position)) => {
+                tx_clone.send(Event::PleaseSeek(position)).unwrap()
+            }
+            MediaControlEvent::OpenUri(uri) => tx_clone.send(Event::PleaseOpenUri(uri)).unwrap(),
+            // There's no window to bring up in a terminal
+            MediaControlEvent::Raise => (),
+            MediaControlEvent::Quit => tx_clone.send(Event::PleaseQuit).unwrap(),
         })?
     };
 
@@ -318,7 +2042,7 @@ async fn main() -> Result<(), Error> {
                 //.margin(2)
                 .constraints(
                     [
-                        Constraint::Length(3),
+                        Constraint::Length(4),
                         Constraint::Min(1),
                         Constraint::Length(3),
                     ]
@@ -326,25 +2050,84 @@ async fn main() -> Result<(), Error> {
                 )
                 .split(master_chunks[0]);
 
-            let np = Paragraph::new(app.np.as_ref()).block(
-                Block::default().borders(Borders::ALL).title(format!(
-                    "{} {}",
-                    if app.player.current == Platform::Spotify {
-                        ""
-                    } else {
-                        ""
-                    },
-                    if app.paused { "Paused" } else { "Now Playing" }
-                )),
-            );
-            f.render_widget(np, chunks_left[0]);
-
-            let input = Paragraph::new(app.input.as_ref())
-                .style(match app.input_mode {
-                    InputMode::Normal => Style::default(),
-                    InputMode::Editing => Style::default().fg(Color::LightGreen),
-                })
-                .block(Block::default().borders(Borders::ALL).title("Input"));
+            let np_block = Block::default().borders(Borders::ALL).title(format!(
+                "{} {}{}{}{} | Volume: {}%",
+                app.player.current.icon(),
+                if app.paused { "Paused" } else { "Now Playing" },
+                match app.repeat {
+                    Repeat::Off => "",
+                    Repeat::One => " | Repeat one",
+                    Repeat::All => " | Repeat all",
+                },
+                if app.shuffle { " | Shuffle" } else { "" },
+                if app.config.autoplay {
+                    " | Autoplay"
+                } else {
+                    ""
+                },
+                app.config.volume
+            ));
+            let np_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Length(1)].as_ref())
+                .split(np_block.inner(chunks_left[0]));
+            f.render_widget(np_block, chunks_left[0]);
+
+            let np = Paragraph::new(match &app.current {
+                Some(current) if app.is_saved(current) => format!("♥ {}", app.np),
+                _ => app.np.clone(),
+            });
+            f.render_widget(np, np_chunks[0]);
+
+            let (ratio, label) = match app
+                .download
+                .as_ref()
+                .filter(|download| app.player.youtube.is_loading(&download.video_id))
+            {
+                Some(download) => (
+                    download.percent / 100.0,
+                    format!("Downloading {:.0}% at {}", download.percent, download.speed),
+                ),
+                None => {
+                    let position = app.player.position();
+                    (
+                        match app.player.duration() {
+                            Some(duration) if !duration.is_zero() => {
+                                position.as_secs_f64() / duration.as_secs_f64()
+                            }
+                            _ => 0.0,
+                        },
+                        format!(
+                            "{} / {}",
+                            format_duration(position),
+                            app.player
+                                .duration()
+                                .map(format_duration)
+                                .unwrap_or_else(|| String::from("-:--"))
+                        ),
+                    )
+                }
+            };
+            let progress = LineGauge::default()
+                .gauge_style(Style::default().fg(app.player.current.color()))
+                .line_set(symbols::line::THICK)
+                .ratio(ratio)
+                .label(label);
+            f.render_widget(progress, np_chunks[1]);
+
+            let input =
+                Paragraph::new(app.input.as_ref())
+                    .style(match app.input_mode {
+                        InputMode::Normal => Style::default(),
+                        InputMode::Editing => Style::default().fg(Color::LightGreen),
+                    })
+                    .block(Block::default().borders(Borders::ALL).title(
+                        if app.status.is_empty() {
+                            String::from("Input")
+                        } else {
+                            format!("Input | {}", app.status)
+                        },
+                    ));
             f.render_widget(input, chunks_left[2]);
             match app.input_mode {
                 InputMode::Normal => {}
@@ -357,28 +2140,56 @@ async fn main() -> Result<(), Error> {
 
             let list: Vec<ListItem> = app
                 .results
-                .0
                 .items
                 .iter()
                 .enumerate()
                 .map(|(i, m)| {
-                    let content = vec![Spans::from(Span::raw(format!("{}: {}", i, m)))];
+                    if let Item::Stat(line) = m {
+                        return ListItem::new(line.as_str());
+                    }
+                    let heart = match m {
+                        Item::Track(track) if app.is_saved(track) => "♥ ",
+                        _ => "",
+                    };
+                    let resume = match m {
+                        Item::Track(Track {
+                            uri: Uri::Episode(uri),
+                            ..
+                        }) => app
+                            .episodes
+                            .get(uri)
+                            .map(|position| format!(" (resume at {})", format_duration(position)))
+                            .unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    let platform = m.platform();
+                    let content = vec![Spans::from(vec![
+                        Span::raw(format!("{}: ", i)),
+                        Span::styled(
+                            format!(" {} ", platform.icon()),
+                            Style::default().fg(Color::Black).bg(platform.color()),
+                        ),
+                        Span::raw(format!(" {}{}{}", heart, m, resume)),
+                    ])];
                     ListItem::new(content)
                 })
                 .collect();
             let results = List::new(list)
-                .block(Block::default().borders(Borders::ALL).title("Tracks"))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(app.results_title.as_str()),
+                )
                 .highlight_style(
                     Style::default()
-                        .bg(if app.results.1 == Platform::Spotify {
-                            Color::LightGreen
-                        } else {
-                            Color::LightRed
-                        })
+                        .bg(app
+                            .results
+                            .get_selection()
+                            .map_or(Color::LightGreen, |item| item.platform().color()))
                         .add_modifier(Modifier::BOLD),
                 );
 
-            f.render_stateful_widget(results, chunks_left[1], &mut app.results.0.state);
+            f.render_stateful_widget(results, chunks_left[1], &mut app.results.state);
 
             let tracks: Vec<ListItem> = app
                 .queue
@@ -402,27 +2213,38 @@ async fn main() -> Result<(), Error> {
             }
         })?;
 
-        if let (Some(controls), Some(current)) =
-            (app.os_media_controls.borrow_mut(), app.current.clone())
-        {
-            controls.set_metadata(MediaMetadata {
-                title: Some(&current.name),
-                artist: Some(&current.artist),
-                ..Default::default()
-            })?;
+        let duration = app.player.duration();
+        if let Some(metadata) = app.metadata.as_mut() {
+            // YouTube and local durations are only known once the audio is loaded
+            metadata.duration = metadata.duration.or(duration);
+        }
+        if app.metadata != app.sent_metadata {
+            if let (Some(controls), Some(metadata)) =
+                (app.os_media_controls.borrow_mut(), app.metadata.as_ref())
+            {
+                // souvlaki 0.5 has no field for `mpris:trackid`, so the track id
+                // can't be sent
+                controls.set_metadata(MediaMetadata {
+                    title: Some(&metadata.title),
+                    artist: Some(&metadata.artist),
+                    album: metadata.album.as_deref(),
+                    cover_url: metadata.cover_url.as_deref(),
+                    duration: metadata.duration,
+                })?;
+            }
+            app.sent_metadata = app.metadata.clone();
         }
 
-        if app.player.current == Platform::Youtube && app.player.youtube.sink.empty() {
-            if let Some(next) = app.queue.first() {
-                app.player.play(next.uri.clone()).await;
-                if let Uri::Youtube(_) = next.uri {
-                    app.np = next.name.clone()
-                }
-                app.queue.remove(0);
-                app.set_playback_state(false)?;
+        if app.current.is_some() && app.player.is_idle() {
+            if let Err(err) = app.play_next(false).await {
+                app.status = err.to_string();
             }
         }
 
+        if let Err(err) = app.check_saved().await {
+            app.status = format!("Couldn't check saved tracks: {}", err);
+        }
+
         // Handle input
         match events.next()? {
             Event::Input(input) => match app.input_mode {
@@ -431,71 +2253,109 @@ async fn main() -> Result<(), Error> {
                         app.input_mode = InputMode::Editing;
                     }
                     Key::Down => {
-                        app.results.0.next();
+                        app.results.next();
+                        if let Err(err) = app.load_more().await {
+                            app.status = format!("Couldn't load more: {}", err);
+                        }
                     }
                     Key::Up => {
-                        app.results.0.previous();
+                        app.results.previous();
+                        if let Err(err) = app.load_more().await {
+                            app.status = format!("Couldn't load more: {}", err);
+                        }
                     }
-                    Key::Char('e') => {
-                        if let Some(controls) = app.os_media_controls.borrow_mut() {
-                            controls.detach()?;
+                    Key::Left => {
+                        if let Err(err) = app.player.seek_by(-SEEK_STEP) {
+                            app.status = err.to_string();
                         }
-                        return Ok(());
+                        app.set_playback_state(app.paused)?;
                     }
-                    Key::Char('\n') => {
+                    Key::Right => {
+                        if let Err(err) = app.player.seek_by(SEEK_STEP) {
+                            app.status = err.to_string();
+                        }
+                        app.set_playback_state(app.paused)?;
+                    }
+                    Key::Char('+') => {
                         app.player
-                            .play(app.results.0.get_selection().uri.clone())
-                            .await;
-                        if let Uri::Youtube(_) = app.results.0.get_selection().uri.clone() {
-                            app.np = app.results.0.get_selection().name.clone();
+                            .set_volume(app.player.volume().saturating_add(VOLUME_STEP));
+                    }
+                    Key::Char('-') => {
+                        app.player
+                            .set_volume(app.player.volume().saturating_sub(VOLUME_STEP));
+                    }
+                    Key::Char('e') => break,
+                    Key::Char('\n') => {
+                        if let Err(err) = app.select().await {
+                            app.status = err.to_string();
                         }
-                        app.set_playback_state(false)?;
-                        app.current = Some(app.results.0.get_selection().clone());
                     }
                     Key::Char('a') => {
-                        let selection = &(*app.results.0.get_selection());
-                        app.queue.push(Track::new(
-                            selection.name.to_string(),
-                            selection.artist.to_string(),
-                            selection.uri.clone(),
-                        ));
+                        if let Err(err) = app.queue_selection().await {
+                            app.status = err.to_string();
+                        }
+                    }
+                    Key::Char('A') => {
+                        let tracks = app.result_tracks(0);
+                        app.status = format!("Queued {} tracks", tracks.len());
+                        app.queue.extend(tracks);
+                    }
+                    Key::Char('p') => {
+                        if let Err(err) = app.play_from_selection().await {
+                            app.status = err.to_string();
+                        }
                     }
                     Key::Char('q') => {
                         app.toggle_queue = !app.toggle_queue;
                     }
                     Key::Char(',') => {
-                        if let Some(previous) = app.history.first() {
-                            app.player.play(previous.uri.clone()).await;
-                            if let Uri::Youtube(_) = previous.uri {
-                                app.np = previous.name.clone()
-                            }
-                            if let Some(current) = app.current.clone() {
-                                app.queue.insert(0, current);
-                            }
-                            app.current = Some(previous.clone());
-                            app.history.remove(0);
-                            app.set_playback_state(false)?;
+                        if let Err(err) = app.play_previous().await {
+                            app.status = err.to_string();
                         }
                     }
                     Key::Char('.') => {
-                        if let Some(next) = app.queue.first() {
-                            app.player.play(next.uri.clone()).await;
-                            if let Uri::Youtube(_) = next.uri {
-                                app.np = next.name.clone()
-                            }
-                            if let Some(current) = app.current.clone() {
-                                app.history.insert(0, current);
-                            }
-                            app.current = Some(next.clone());
-                            app.queue.remove(0);
-                            app.set_playback_state(false)?;
+                        if let Err(err) = app.play_next(true).await {
+                            app.status = err.to_string();
+                        }
+                    }
+                    Key::Char('r') => {
+                        app.repeat = app.repeat.next();
+                    }
+                    Key::Char('s') => {
+                        app.shuffle = !app.shuffle;
+                    }
+                    Key::Char('l') => {
+                        let track = match app.results.get_selection() {
+                            Some(Item::Track(track)) => Some(track.clone()),
+                            _ => None,
+                        };
+                        if let Err(err) = app.toggle_saved(track).await {
+                            app.status = err.to_string();
+                        }
+                    }
+                    Key::Char('g') => {
+                        if let Err(err) = app.go_to_album().await {
+                            app.status = err.to_string();
+                        }
+                    }
+                    Key::Char('G') => {
+                        if let Err(err) = app.go_to_artist().await {
+                            app.status = err.to_string();
+                        }
+                    }
+                    Key::Backspace => app.go_back(),
+                    Key::Char('L') => {
+                        if let Err(err) = app.toggle_saved(app.current.clone()).await {
+                            app.status = err.to_string();
                         }
                     }
                     _ => {}
                 },
                 InputMode::Editing => match input {
                     Key::Char('\n') => {
-                        app.handle_command().await?;
+                        if let Err(err) = app.handle_command().await {
+                            app.status = err.to_string();
+                        }
                         app.input_mode = InputMode::Normal;
                     }
                     Key::Char(c) => {
@@ -511,53 +2371,186 @@ async fn main() -> Result<(), Error> {
                 },
             },
 
+            Event::UpdateNP(episode) if episode.audio_type == SpotifyAudioType::Podcast => {
+                let session = app.player.spotify.get_session().clone();
+                match episode_metadata(&session, episode).await {
+                    Ok(metadata) => {
+                        app.np = format!(
+                            "{} - {}",
+                            metadata.album.as_deref().unwrap_or_default(),
+                            metadata.title
+                        );
+                        app.metadata = Some(metadata);
+                    }
+                    Err(err) => {
+                        app.status = format!("Couldn't load the episode's metadata: {}", err)
+                    }
+                }
+            }
+
             Event::UpdateNP(track) => {
-                app.np = {
-                    let data =
-                        librespot::metadata::Track::get(app.player.spotify.get_session(), track)
-                            .await?;
-                    format!(
-                        "{} - {}",
-                        join_all(data.artists.iter().map(|id| {
-                            let cloned_session = app.player.spotify.get_session().clone();
-
-                            async move {
-                                librespot::metadata::Artist::get(&cloned_session, *id)
-                                    .await
-                                    .unwrap()
-                                    .name
-                            }
-                        }))
-                        .await
-                        .join(", "),
-                        data.name
-                    )
+                let session = app.player.spotify.get_session().clone();
+                // The previous metadata stays if the new one can't be fetched
+                match track_metadata(&session, track).await {
+                    Ok(metadata) => {
+                        app.np = format!("{} - {}", metadata.artist, metadata.title);
+                        app.metadata = Some(metadata);
+                    }
+                    Err(err) => app.status = format!("Couldn't load the track's metadata: {}", err),
                 }
             }
 
+            Event::UpdateProgress(position, duration) => {
+                app.player.sync(position, duration);
+                app.set_playback_state(app.paused)?;
+            }
+
+            Event::UpdateVolume(volume) => {
+                // Rounded, so the percentage set with `Player::set_volume` comes back unchanged
+                app.config.volume =
+                    ((volume as u32 * 100 + u16::MAX as u32 / 2) / u16::MAX as u32) as u8;
+                app.config.save()?;
+            }
+
             Event::TrackEnded => {
-                if let Some(next) = app.queue.first() {
-                    app.player.play(next.uri.clone()).await;
-                    if let Uri::Youtube(_) = next.uri {
-                        app.np = next.name.clone()
-                    }
-                    app.queue.remove(0);
-                    app.set_playback_state(false)?;
-                } else if let Some(controls) = app.os_media_controls.borrow_mut() {
-                    controls.set_playback(MediaPlayback::Stopped)?
+                if let Err(err) = app.play_next(false).await {
+                    app.status = err.to_string();
                 }
             }
 
             Event::PleasePause => {
                 app.player.pause();
+                if let Err(err) = app.save_episode_position() {
+                    app.status = err.to_string();
+                }
                 app.set_playback_state(true)?;
             }
             Event::PleaseResume => {
                 app.player.resume();
                 app.set_playback_state(false)?;
             }
+            Event::PleaseToggle => {
+                if let Err(err) = app.toggle_pause() {
+                    app.status = err.to_string();
+                }
+            }
+            Event::PleaseStop => {
+                if let Err(err) = app.stop() {
+                    app.status = err.to_string();
+                }
+            }
+            Event::PleaseNext => {
+                if let Err(err) = app.play_next(true).await {
+                    app.status = err.to_string();
+                }
+            }
+            Event::PleasePrevious => {
+                if let Err(err) = app.play_previous().await {
+                    app.status = err.to_string();
+                }
+            }
+            Event::PleaseOpenUri(uri) => {
+                if let Err(err) = app.open_uri(uri).await {
+                    app.status = err.to_string();
+                }
+            }
+            Event::PleaseQuit => break,
+            Event::PleaseSeek(position) => {
+                if let Err(err) = app.player.seek(position) {
+                    app.status = err.to_string();
+                }
+                app.set_playback_state(app.paused)?;
+            }
+            Event::PleaseSeekBy(offset) => {
+                if let Err(err) = app.player.seek_by(offset) {
+                    app.status = err.to_string();
+                }
+                app.set_playback_state(app.paused)?;
+            }
+
+            Event::DownloadProgress(progress) => app.download = Some(progress),
+            Event::DownloadReady(video_id, download) => {
+                if let Err(err) = app.player.start_download(&video_id, download) {
+                    app.np = format!("Error: {}", err);
+                }
+                app.set_playback_state(app.paused)?;
+            }
+            Event::DownloadFailed(video_id, err) => {
+                if app.player.youtube.is_loading(&video_id) {
+                    app.player.youtube.cancel();
+                    app.np = format!("Error: {}", err);
+                }
+            }
 
-            Event::Tick => (),
+            Event::LibrarySynced(library) => {
+                app.syncing = false;
+                app.library = library;
+                // The sync may have fetched them before they were unsaved
+                for uri in std::mem::take(&mut app.unsaved_while_syncing) {
+                    app.library.remove(&uri);
+                }
+                app.library.save()?;
+                app.remember_library();
+                if let View::Library { .. } = app.view {
+                    app.show_library();
+                }
+                if app.resync {
+                    app.resync = false;
+                    app.sync_library();
+                }
+            }
+            Event::LibrarySyncFailed(err) => {
+                app.syncing = false;
+                app.resync = false;
+                app.unsaved_while_syncing.clear();
+                app.status = format!("Couldn't sync the library: {}", err);
+            }
+            Event::LocalScanned(scan) => {
+                let status = match scan.skipped.len() {
+                    0 => format!(
+                        "Found {} tracks in {}",
+                        scan.tracks.len(),
+                        scan.dir.display()
+                    ),
+                    skipped => format!(
+                        "Found {} tracks in {}, skipped {} unreadable folders",
+                        scan.tracks.len(),
+                        scan.dir.display(),
+                        skipped
+                    ),
+                };
+                app.status = match app.local.add(scan) {
+                    Ok(()) => status,
+                    Err(err) => err.to_string(),
+                };
+            }
+            Event::LocalScanFailed(dir, err) => {
+                app.status = format!("Couldn't scan {}: {}", dir.display(), err);
+            }
+            Event::YoutubePlaylistLoaded(playlist) => {
+                let page = app.page();
+                app.status = format!("{} videos", playlist.videos.len());
+                app.set_results(&playlist.title, youtube_items(playlist.videos));
+                app.back.push(page);
+            }
+            Event::YoutubePlaylistFailed(err) => app.status = err,
+
+            Event::Tick => {
+                if !app.paused && app.episode_saved.elapsed() >= EPISODE_SAVE_INTERVAL {
+                    if let Err(err) = app.save_episode_position() {
+                        // Tried again after the next interval, rather than on every tick
+                        app.episode_saved = Instant::now();
+                        app.status = err.to_string();
+                    }
+                }
+            }
         }
     }
+
+    app.save_episode_position()?;
+    app.record_play(true)?;
+    if let Some(controls) = app.os_media_controls.borrow_mut() {
+        controls.detach()?;
+    }
+    Ok(())
 }
@@ -1,46 +1,253 @@
-use rodio::OutputStreamHandle;
+use rodio::{OutputStreamHandle, Sink};
+use std::{
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+use termion::event::Key;
 
-use crate::{spotify::SpotifyPlayer, youtube::YoutubeClient, NeoResult, Platform, Uri};
+use crate::{
+    cache::Cache,
+    error::Error,
+    event::Event,
+    local::LocalPlayer,
+    spotify::SpotifyPlayer,
+    youtube::{Download, YoutubeClient},
+    NeoResult, Platform, Uri,
+};
+
+/// Creates a sink with the same volume and paused state as `previous`, to replace it
+pub fn new_sink(handle: &OutputStreamHandle, previous: &Sink) -> NeoResult<Sink> {
+    let sink = Sink::try_new(handle).map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))?;
+    sink.set_volume(previous.volume());
+    if previous.is_paused() {
+        sink.pause();
+    }
+    Ok(sink)
+}
+
+/// Keeps track of how far into the current track playback is, since none of
+/// the backends report it on demand
+#[derive(Default)]
+pub struct Clock {
+    elapsed: Duration,
+    since: Option<Instant>,
+    /// Time spent playing the current track before `since`, which seeking doesn't change
+    played: Duration,
+}
+
+impl Clock {
+    pub fn start(&mut self, position: Duration) {
+        self.elapsed = position;
+        self.since = Some(Instant::now());
+        self.played = Duration::ZERO;
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(since) = self.since.take() {
+            self.elapsed += since.elapsed();
+            self.played += since.elapsed();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.since.is_none() {
+            self.since = Some(Instant::now());
+        }
+    }
+
+    pub fn set(&mut self, position: Duration) {
+        self.elapsed = position;
+        if let Some(since) = self.since {
+            self.played += since.elapsed();
+            self.since = Some(Instant::now());
+        }
+    }
+
+    pub fn position(&self) -> Duration {
+        self.elapsed + self.since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+
+    /// How long the current track has been playing for, leaving out pauses and
+    /// the parts skipped by seeking
+    pub fn played(&self) -> Duration {
+        self.played + self.since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+}
 
 pub struct Player {
     pub youtube: YoutubeClient,
     pub spotify: SpotifyPlayer,
+    pub local: LocalPlayer,
     pub current: Platform,
+    clock: Clock,
+    duration: Option<Duration>,
+    volume: u8,
+    /// Whether the current track started playing, as opposed to still loading or failing to
+    started: bool,
 }
 
 impl Player {
-    pub async fn new(osh: OutputStreamHandle) -> NeoResult<Self> {
+    pub async fn new(
+        osh: OutputStreamHandle,
+        events: Sender<Event<Key>>,
+        cache: Cache,
+    ) -> NeoResult<Self> {
         Ok(Self {
-            youtube: YoutubeClient::new(osh)?,
+            youtube: YoutubeClient::new(osh.clone(), events, cache)?,
             spotify: SpotifyPlayer::new().await.unwrap(),
+            local: LocalPlayer::new(osh)?,
             current: Platform::Spotify,
+            clock: Clock::default(),
+            duration: None,
+            volume: 100,
+            started: false,
         })
     }
 
-    pub async fn play(&mut self, uri: Uri) {
-        match uri {
-            Uri::Spotify(uri) => {
-                self.spotify.play(uri).await.unwrap();
-                self.current = Platform::Spotify
+    /// Plays a track or episode from `start` into it. YouTube videos always
+    /// start from the beginning, since they have to be downloaded first
+    pub async fn play(&mut self, uri: Uri, start: Duration) -> NeoResult<()> {
+        // Only one backend should be heard at a time, and the previous one has to
+        // report itself idle should this track fail to load
+        self.stop()?;
+        self.youtube.cancel();
+        self.started = false;
+
+        let start = match uri {
+            Uri::Spotify(uri) | Uri::Episode(uri) => {
+                self.spotify.play(uri, start).await?;
+                self.current = Platform::Spotify;
+                // Filled in once librespot reports the track as playing
+                self.duration = None;
+                start
             }
             Uri::Youtube(video_id) => {
-                self.youtube.play(video_id).await;
-                self.current = Platform::Youtube
+                self.youtube.play(video_id);
+                self.current = Platform::Youtube;
+                self.duration = None;
+                // The clock starts with `start_download`, once there's audio to play
+                self.clock = Clock::default();
+                return Ok(());
+            }
+            Uri::Local(path) => {
+                self.local.play(path)?;
+                if !start.is_zero() {
+                    self.local.seek(start)?;
+                }
+                self.current = Platform::Local;
+                self.duration = self.local.duration;
+                start
             }
         };
+        self.clock.start(start);
+        self.started = true;
+        Ok(())
+    }
+
+    /// Starts a YouTube video once its download is ready
+    pub fn start_download(&mut self, video_id: &str, download: Download) -> NeoResult<()> {
+        if self.youtube.start(video_id, download)? {
+            self.duration = self.youtube.duration;
+            self.clock.start(Duration::ZERO);
+            self.started = true;
+        }
+        Ok(())
     }
 
     pub fn pause(&mut self) {
         match self.current {
             Platform::Spotify => self.spotify.pause(),
             Platform::Youtube => self.youtube.pause(),
+            Platform::Local => self.local.pause(),
+        }
+        self.clock.pause();
+    }
+
+    /// Drops what the current backend had loaded
+    fn stop(&mut self) -> NeoResult<()> {
+        match self.current {
+            Platform::Spotify => self.spotify.stop(),
+            Platform::Youtube => self.youtube.stop()?,
+            Platform::Local => self.local.stop()?,
         }
+        self.clock.pause();
+        Ok(())
     }
 
     pub fn resume(&mut self) {
         match self.current {
             Platform::Spotify => self.spotify.resume(),
             Platform::Youtube => self.youtube.resume(),
+            Platform::Local => self.local.resume(),
+        }
+        self.clock.resume();
+    }
+
+    /// Jumps to an absolute position in the current track
+    pub fn seek(&mut self, position: Duration) -> NeoResult<()> {
+        match self.current {
+            Platform::Spotify => self.spotify.seek(position),
+            Platform::Youtube => self.youtube.seek(position)?,
+            Platform::Local => self.local.seek(position)?,
         }
+        self.clock.set(position);
+        Ok(())
+    }
+
+    /// Moves the playback position by `offset` milliseconds, backwards if negative
+    pub fn seek_by(&mut self, offset: i64) -> NeoResult<()> {
+        let position = self.position().as_millis() as i64 + offset;
+        self.seek(Duration::from_millis(position.max(0) as u64))
+    }
+
+    /// Corrects the clock with the position and duration reported by librespot
+    pub fn sync(&mut self, position: Duration, duration: Duration) {
+        if self.current == Platform::Spotify {
+            self.clock.set(position);
+            self.duration = Some(duration);
+        }
+    }
+
+    /// Whether the current track finished playing. librespot reports this
+    /// through `Event::TrackEnded` instead
+    pub fn is_idle(&self) -> bool {
+        match self.current {
+            Platform::Spotify => false,
+            Platform::Youtube => self.youtube.is_idle(),
+            Platform::Local => self.local.is_idle(),
+        }
+    }
+
+    pub fn position(&self) -> Duration {
+        match self.duration {
+            Some(duration) => self.clock.position().min(duration),
+            None => self.clock.position(),
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn has_started(&self) -> bool {
+        self.started
+    }
+
+    /// How long the current track was actually heard, unlike its position
+    pub fn played(&self) -> Duration {
+        self.clock.played()
+    }
+
+    /// Sets the volume of all backends, from 0 to 100
+    pub fn set_volume(&mut self, volume: u8) {
+        let volume = volume.min(100);
+        self.spotify.set_volume(volume);
+        self.youtube.set_volume(volume);
+        self.local.set_volume(volume);
+        self.volume = volume;
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
     }
 }
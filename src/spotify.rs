@@ -7,13 +7,26 @@ use librespot::{
     playback::{
         audio_backend,
         config::{AudioFormat, PlayerConfig},
+        mixer::{softmixer::SoftMixer, Mixer, MixerConfig},
         player::{Player, PlayerEventChannel},
     },
 };
 use rspotify::{prelude::*, AuthCodeSpotify};
 use rspotify_model::{
-    enums::types::SearchType, page::Page, search::SearchResult, track::FullTrack,
+    album::{FullAlbum, SimplifiedAlbum},
+    artist::FullArtist,
+    enums::{
+        misc::{Market, TimeRange},
+        types::SearchType,
+    },
+    idtypes::{AlbumId, ArtistId, EpisodeId, PlaylistId, ShowId, TrackId},
+    playlist::{FullPlaylist, ItemPositions, SimplifiedPlaylist},
+    search::SearchResult,
+    show::{FullEpisode, FullShow, SimplifiedEpisode},
+    track::{FullTrack, SavedTrack, SimplifiedTrack},
+    PlayableItem,
 };
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct SpotifyClient {
@@ -22,6 +35,7 @@ pub struct SpotifyClient {
 
 pub struct SpotifyPlayer {
     player: Player,
+    mixer: SoftMixer,
     session: Session,
     token: Token,
 }
@@ -41,14 +55,20 @@ impl SpotifyPlayer {
         let session = Session::new(session_config, None);
         session.connect(credentials).await?;
 
-        let (player, _) = Player::new(player_config, session.clone(), None, move || {
-            backend(None, audio_format)
-        });
+        let mixer = SoftMixer::open(MixerConfig::default());
+
+        let (player, _) = Player::new(
+            player_config,
+            session.clone(),
+            mixer.get_audio_filter(),
+            move || backend(None, audio_format),
+        );
 
-        let token = session.token_provider().get_token("app-remote-control,streaming,user-library-read,user-read-currently-playing,user-read-playback-state,user-read-playback-position,playlist-read-collaborative,playlist-read-private,user-library-modify,user-modify-playback-state").await?;
+        let token = session.token_provider().get_token("app-remote-control,streaming,user-library-read,user-read-currently-playing,user-read-playback-state,user-read-playback-position,playlist-read-collaborative,playlist-read-private,playlist-modify-public,playlist-modify-private,user-library-modify,user-read-recently-played,user-top-read,user-modify-playback-state").await?;
 
         Ok(Self {
             player,
+            mixer,
             session,
             token,
         })
@@ -66,8 +86,10 @@ impl SpotifyPlayer {
         &self.token
     }
 
-    pub async fn play(&mut self, uri: String) -> Result<(), Error> {
-        self.player.load(SpotifyId::from_uri(&uri)?, true, 0);
+    /// Plays a track or podcast episode from `start` into it
+    pub async fn play(&mut self, uri: String, start: Duration) -> Result<(), Error> {
+        self.player
+            .load(SpotifyId::from_uri(&uri)?, true, start.as_millis() as u32);
         self.player.play();
 
         Ok(())
@@ -77,9 +99,24 @@ impl SpotifyPlayer {
         self.player.pause();
     }
 
+    pub fn stop(&mut self) {
+        self.player.stop();
+    }
+
     pub fn resume(&mut self) {
         self.player.play();
     }
+
+    pub fn seek(&mut self, position: Duration) {
+        self.player.seek(position.as_millis() as u32);
+    }
+
+    /// Sets the mixer volume from a 0-100 percentage
+    pub fn set_volume(&mut self, volume: u8) {
+        let volume = ((volume as u32 * u16::MAX as u32 + 50) / 100) as u16;
+        self.mixer.set_volume(volume);
+        self.player.emit_volume_set_event(volume);
+    }
 }
 
 impl SpotifyClient {
@@ -89,33 +126,320 @@ impl SpotifyClient {
         }
     }
 
-    pub async fn search(&self, query: String) -> Result<Vec<FullTrack>, Error> {
-        if let SearchResult::Tracks(page) = &self
+    pub async fn track(&self, uri: &str) -> Result<FullTrack, Error> {
+        Ok(self.client.track(&TrackId::from_uri(uri)?).await?)
+    }
+
+    pub async fn episode(&self, uri: &str) -> Result<FullEpisode, Error> {
+        Ok(self
+            .client
+            .get_an_episode(&EpisodeId::from_uri(uri)?, Some(&Market::FromToken))
+            .await?)
+    }
+
+    pub async fn album(&self, uri: &str) -> Result<FullAlbum, Error> {
+        Ok(self.client.album(&AlbumId::from_uri(uri)?).await?)
+    }
+
+    pub async fn artist(&self, uri: &str) -> Result<FullArtist, Error> {
+        Ok(self.client.artist(&ArtistId::from_uri(uri)?).await?)
+    }
+
+    pub async fn playlist(&self, uri: &str) -> Result<FullPlaylist, Error> {
+        Ok(self
+            .client
+            .playlist(&PlaylistId::from_uri(uri)?, None, None)
+            .await?)
+    }
+
+    pub async fn show(&self, uri: &str) -> Result<FullShow, Error> {
+        Ok(self
+            .client
+            .get_a_show(&ShowId::from_uri(uri)?, Some(&Market::FromToken))
+            .await?)
+    }
+
+    /// Searches for tracks, albums, artists, playlists or shows, depending on
+    /// `search_type`, skipping the first `offset` results
+    pub async fn search(
+        &self,
+        query: &str,
+        search_type: SearchType,
+        offset: u32,
+    ) -> Result<SearchResult, Error> {
+        Ok(self
             .client
-            .search(&query, &SearchType::Track, None, None, Some(20), None)
+            .search(query, &search_type, None, None, Some(20), Some(offset))
+            .await?)
+    }
+
+    /// Gets the latest 50 episodes of a show
+    pub async fn show_episodes(&self, uri: &str) -> Result<Vec<SimplifiedEpisode>, Error> {
+        Ok(self
+            .client
+            .get_shows_episodes_manual(
+                &ShowId::from_uri(uri)?,
+                Some(&Market::FromToken),
+                Some(50),
+                None,
+            )
             .await?
-        {
-            Ok(page.items.clone())
-        } else {
-            Ok(vec![])
+            .items)
+    }
+
+    /// Gets every track of an album, in order
+    pub async fn album_tracks(&self, uri: &str) -> Result<Vec<SimplifiedTrack>, Error> {
+        let id = AlbumId::from_uri(uri)?;
+        let mut tracks = vec![];
+        loop {
+            let page = self
+                .client
+                .album_track_manual(&id, Some(50), Some(tracks.len() as u32))
+                .await?;
+            let last = page.next.is_none() || page.items.is_empty();
+            tracks.extend(page.items);
+            if last {
+                return Ok(tracks);
+            }
         }
     }
 
-    // TODO: Implement paging instead of fetching all tracks at once
-    pub async fn get_library(&mut self) -> Vec<FullTrack> {
-        let mut library = vec![];
-        let mut offset = 0;
-        while let Ok(Page { items, total, .. }) = self
+    /// Gets the most popular tracks of an artist in the user's country
+    pub async fn artist_top_tracks(&self, uri: &str) -> Result<Vec<FullTrack>, Error> {
+        Ok(self
             .client
-            .current_user_saved_tracks_manual(None, Some(50), Some(offset))
-            .await
-        {
-            library.extend(items.into_iter().map(|saved| saved.track));
-            if offset > total {
-                break;
+            .artist_top_tracks(&ArtistId::from_uri(uri)?, &Market::FromToken)
+            .await?)
+    }
+
+    /// Gets the latest 50 albums and singles of an artist
+    pub async fn artist_albums(&self, uri: &str) -> Result<Vec<SimplifiedAlbum>, Error> {
+        Ok(self
+            .client
+            .artist_albums_manual(
+                &ArtistId::from_uri(uri)?,
+                None,
+                Some(&Market::FromToken),
+                Some(50),
+                None,
+            )
+            .await?
+            .items)
+    }
+
+    pub async fn related_artists(&self, uri: &str) -> Result<Vec<FullArtist>, Error> {
+        Ok(self
+            .client
+            .artist_related_artists(&ArtistId::from_uri(uri)?)
+            .await?)
+    }
+
+    /// Gets tracks similar to the seeds, which are up to 5 Spotify track URIs
+    pub async fn recommendations(&self, seeds: &[String]) -> Result<Vec<SimplifiedTrack>, Error> {
+        let seeds = seeds
+            .iter()
+            .filter_map(|uri| TrackId::from_uri(uri).ok())
+            .collect::<Vec<_>>();
+
+        Ok(self
+            .client
+            .recommendations(
+                std::iter::empty(),
+                None::<Vec<&ArtistId>>,
+                None::<Vec<&str>>,
+                Some(seeds.iter()),
+                None,
+                Some(20),
+            )
+            .await?
+            .tracks)
+    }
+
+    /// Gets every playlist the user owns or follows
+    pub async fn playlists(&self) -> Result<Vec<SimplifiedPlaylist>, Error> {
+        let mut playlists = vec![];
+        loop {
+            let page = self
+                .client
+                .current_user_playlists_manual(Some(50), Some(playlists.len() as u32))
+                .await?;
+            let last = page.next.is_none() || page.items.is_empty();
+            playlists.extend(page.items);
+            if last {
+                return Ok(playlists);
+            }
+        }
+    }
+
+    /// Gets the tracks and episodes of a playlist in order, with their position
+    /// in it, leaving out its local files, which can't be played
+    pub async fn playlist_tracks(&self, uri: &str) -> Result<Vec<(u32, PlayableItem)>, Error> {
+        let id = PlaylistId::from_uri(uri)?;
+        let mut tracks = vec![];
+        let mut offset = 0;
+        loop {
+            let page = self
+                .client
+                .playlist_items_manual(&id, None, None, Some(100), Some(offset))
+                .await?;
+            let last = page.next.is_none() || page.items.is_empty();
+            for item in page.items {
+                if let (Some(track), false) = (item.track, item.is_local) {
+                    tracks.push((offset, track));
+                }
+                offset += 1;
+            }
+            if last {
+                return Ok(tracks);
             }
-            offset += 50;
         }
-        library
     }
+
+    /// Creates a private playlist owned by the user
+    pub async fn create_playlist(&self, name: &str) -> Result<FullPlaylist, Error> {
+        let user = self.client.me().await?;
+        Ok(self
+            .client
+            .user_playlist_create(&user.id, name, Some(false), None, None)
+            .await?)
+    }
+
+    /// Appends tracks to the end of a playlist
+    pub async fn add_to_playlist(&self, uri: &str, tracks: &[String]) -> Result<(), Error> {
+        let id = PlaylistId::from_uri(uri)?;
+        // The API takes at most 100 tracks per request
+        for chunk in track_ids(tracks)?.chunks(100) {
+            self.client
+                .playlist_add_items(&id, chunk.iter().map(|id| id as &dyn PlayableId), None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the occurrence of a track at `position` in a playlist, leaving
+    /// other copies of it alone. `position` refers to the version `snapshot_id`
+    pub async fn remove_from_playlist(
+        &self,
+        uri: &str,
+        track: &str,
+        position: u32,
+        snapshot_id: &str,
+    ) -> Result<(), Error> {
+        let track = TrackId::from_uri(track)?;
+        let positions = [position];
+        self.client
+            .playlist_remove_specific_occurrences_of_items(
+                &PlaylistId::from_uri(uri)?,
+                [&ItemPositions {
+                    id: &track,
+                    positions: &positions,
+                }],
+                Some(snapshot_id),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn rename_playlist(&self, uri: &str, name: &str) -> Result<(), Error> {
+        self.client
+            .playlist_change_detail(&PlaylistId::from_uri(uri)?, Some(name), None, None, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Moves the item at position `from` in a playlist so it ends up before the
+    /// item that is at position `before`
+    pub async fn move_in_playlist(&self, uri: &str, from: u32, before: u32) -> Result<(), Error> {
+        self.client
+            .playlist_reorder_items(
+                &PlaylistId::from_uri(uri)?,
+                Some(from as i32),
+                Some(before as i32),
+                None,
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Saves tracks to the user's library
+    pub async fn save_tracks(&self, uris: &[String]) -> Result<(), Error> {
+        for chunk in track_ids(uris)?.chunks(50) {
+            self.client.current_user_saved_tracks_add(chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes tracks from the user's library
+    pub async fn unsave_tracks(&self, uris: &[String]) -> Result<(), Error> {
+        for chunk in track_ids(uris)?.chunks(50) {
+            self.client.current_user_saved_tracks_delete(chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether each track is saved in the user's library
+    pub async fn contains_tracks(&self, uris: &[String]) -> Result<Vec<bool>, Error> {
+        let mut saved = vec![];
+        // The API checks at most 50 tracks per request
+        for chunk in track_ids(uris)?.chunks(50) {
+            saved.extend(
+                self.client
+                    .current_user_saved_tracks_contains(chunk)
+                    .await?,
+            );
+        }
+        Ok(saved)
+    }
+
+    /// Gets a page of the tracks saved in the user's library, most recently saved
+    /// first, along with how many tracks the library has
+    pub async fn get_library(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> Result<(Vec<SavedTrack>, u32), Error> {
+        let page = self
+            .client
+            .current_user_saved_tracks_manual(None, Some(limit), Some(offset))
+            .await?;
+        Ok((page.items, page.total))
+    }
+
+    /// Gets the last 50 tracks the user played, most recent first
+    pub async fn recently_played(&self) -> Result<Vec<FullTrack>, Error> {
+        Ok(self
+            .client
+            .current_user_recently_played(Some(50))
+            .await?
+            .items
+            .into_iter()
+            .map(|history| history.track)
+            .collect())
+    }
+
+    /// Gets the 50 tracks the user listened to the most over a time range
+    pub async fn top_tracks(&self, time_range: TimeRange) -> Result<Vec<FullTrack>, Error> {
+        Ok(self
+            .client
+            .current_user_top_tracks_manual(Some(&time_range), Some(50), None)
+            .await?
+            .items)
+    }
+
+    /// Gets the 50 artists the user listened to the most over a time range
+    pub async fn top_artists(&self, time_range: TimeRange) -> Result<Vec<FullArtist>, Error> {
+        Ok(self
+            .client
+            .current_user_top_artists_manual(Some(&time_range), Some(50), None)
+            .await?
+            .items)
+    }
+}
+
+fn track_ids(uris: &[String]) -> Result<Vec<TrackId>, Error> {
+    Ok(uris
+        .iter()
+        .map(|uri| TrackId::from_uri(uri))
+        .collect::<Result<Vec<_>, _>>()?)
 }
@@ -0,0 +1,40 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, path::PathBuf};
+
+use crate::NeoResult;
+
+/// Where a file named `name` is kept in `$XDG_DATA_HOME/neoplayer`
+pub fn data_path(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("neoplayer").join(name))
+}
+
+/// Where a file named `name` is kept in `$XDG_CONFIG_HOME/neoplayer`
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("neoplayer").join(name))
+}
+
+/// Reads a JSON file, falling back to the default if it's missing or unreadable
+pub fn load_json<T: DeserializeOwned + Default>(path: Option<PathBuf>) -> T {
+    path.and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_json<T: Serialize>(path: Option<PathBuf>, value: &T) -> NeoResult<()> {
+    write(path, serde_json::to_string(value)?)
+}
+
+/// Like `save_json`, indented so it's easy to edit by hand
+pub fn save_pretty_json<T: Serialize>(path: Option<PathBuf>, value: &T) -> NeoResult<()> {
+    write(path, serde_json::to_string_pretty(value)?)
+}
+
+fn write(path: Option<PathBuf>, contents: String) -> NeoResult<()> {
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,16 @@
+use std::time::Duration;
 use tui::widgets::ListState;
 
+/// Formats a duration as `m:ss`, or `h:mm:ss` for anything longer than an hour
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
@@ -13,7 +24,17 @@ impl<T> StatefulList<T> {
         }
     }
 
+    /// Replaces the items, selecting the first one
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.state
+            .select(if items.is_empty() { None } else { Some(0) });
+        self.items = items;
+    }
+
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -28,6 +49,9 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -41,7 +65,7 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
-    pub fn get_selection(&self) -> &T {
-        self.items.get(self.state.selected().unwrap()).unwrap()
+    pub fn get_selection(&self) -> Option<&T> {
+        self.items.get(self.state.selected()?)
     }
 }
@@ -1,42 +1,807 @@
-use rodio::{Decoder, OutputStreamHandle, Sink};
+use rodio::{Decoder, OutputStreamHandle, Sink, Source};
 use serde_json::Value;
-use std::{fs::File, io::BufReader};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::{self, Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use termion::event::Key;
 
-use crate::{error::Error, NeoResult};
+use crate::{cache::Cache, error::Error, event::Event, player::new_sink, NeoResult};
+
+/// How long to wait before checking again for audio that hasn't been written yet
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How much audio has to be on disk before playback starts, in bytes
+const READY_SIZE: u64 = 64 * 1024;
+
+/// How many samples are decoded ahead of playback, in chunks of `CHUNK_FRAMES` frames
+const BUFFERED_CHUNKS: usize = 16;
+
+/// How many frames, one sample per channel, are sent to the output thread at a time
+const CHUNK_FRAMES: usize = 1024;
+
+/// Numbers downloads, so each one writes its own part file and can be told
+/// apart from a cancelled download of the same video
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The downloads in progress, by video ID
+type Transcodes = Arc<Mutex<HashMap<String, Transcode>>>;
+
+#[derive(Debug)]
+struct Transcode {
+    generation: u64,
+    /// `None` while yt-dlp is still resolving the stream
+    ffmpeg: Option<Child>,
+}
 
 pub struct YoutubeClient {
     pub sink: Sink,
+    handle: OutputStreamHandle,
+    events: Sender<Event<Key>>,
+    pub cache: Cache,
+    /// Video being downloaded in the background before it can start playing
+    loading: Option<String>,
+    /// Audio of the last track started, kept after `stop` so it can be started again
+    download: Option<Download>,
+    /// Whether the sink holds the audio of `download`, rather than being emptied by `stop`
+    loaded: bool,
+    /// Length of the track currently loaded in the sink, as reported by yt-dlp
+    pub duration: Option<Duration>,
+    transcodes: Transcodes,
+}
+
+#[derive(Debug)]
+pub struct DownloadProgress {
+    pub video_id: String,
+    pub percent: f64,
+    /// Download speed relative to playback, as reported by ffmpeg (e.g. `3.2x`)
+    pub speed: String,
 }
 
 #[derive(Debug)]
 pub struct YoutubeResult {
     pub title: String,
     pub href: String,
+    /// Name of the channel that uploaded the video, empty if unknown
+    pub channel: String,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct YoutubePlaylist {
+    pub title: String,
+    pub videos: Vec<YoutubeResult>,
+}
+
+/// A page of search results, with the token to fetch the next page if there is one
+pub struct YoutubePage {
+    pub results: Vec<YoutubeResult>,
+    pub continuation: Option<String>,
 }
 
 impl YoutubeClient {
-    pub fn new(output_stream_handle: OutputStreamHandle) -> NeoResult<Self> {
+    pub fn new(
+        output_stream_handle: OutputStreamHandle,
+        events: Sender<Event<Key>>,
+        cache: Cache,
+    ) -> NeoResult<Self> {
         Ok(Self {
             sink: Sink::try_new(&output_stream_handle)
                 .map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))?,
+            handle: output_stream_handle,
+            events,
+            cache,
+            loading: None,
+            download: None,
+            loaded: false,
+            duration: None,
+            transcodes: Transcodes::default(),
         })
     }
 
-    pub fn search(query: String) -> NeoResult<Vec<YoutubeResult>> {
-        let resp =
+    pub fn search(query: String) -> NeoResult<YoutubePage> {
+        let html =
             ureq::get(format!("https://www.youtube.com/results?search_query={}", query).as_str())
-                .call()?;
-
-        serde_json::from_str::<Value>(
-            resp.into_string()?
-                .split("{\"itemSectionRenderer\":")
-                .last()
-                .ok_or_else(|| Error::Other(String::from("Split failed.")))?
-                .split("},{\"continuationItemRenderer\":{")
-                .collect::<Vec<&str>>()[0],
-        )?
-        .get("contents")
-        .ok_or_else(|| Error::Other(String::from("Parsing Error: Can't find 'contents'.")))?
+                .call()?
+                .into_string()?;
+
+        let results = parse_videos(
+            serde_json::from_str::<Value>(
+                html.split("{\"itemSectionRenderer\":")
+                    .last()
+                    .ok_or_else(|| Error::Other(String::from("Split failed.")))?
+                    .split("},{\"continuationItemRenderer\":{")
+                    .collect::<Vec<&str>>()[0],
+            )?
+            .get("contents")
+            .ok_or_else(|| Error::Other(String::from("Parsing Error: Can't find 'contents'.")))?,
+        )?;
+
+        Ok(YoutubePage {
+            results,
+            continuation: html
+                .split("\"continuationCommand\":{\"token\":\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .map(String::from),
+        })
+    }
+
+    /// Fetches the search results that follow a page, using its continuation token
+    pub fn search_more(continuation: &str) -> NeoResult<YoutubePage> {
+        let body = serde_json::json!({
+            "context": {
+                "client": {
+                    "clientName": "WEB",
+                    "clientVersion": "2.20220301.00.00",
+                },
+            },
+            "continuation": continuation,
+        });
+        let resp = serde_json::from_str::<Value>(
+            &ureq::post("https://www.youtube.com/youtubei/v1/search")
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())?
+                .into_string()?,
+        )?;
+
+        let items = resp
+            .pointer(
+                "/onResponseReceivedCommands/0/appendContinuationItemsAction/continuationItems",
+            )
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                Error::Other(String::from(
+                    "Parsing Error: Can't find 'continuationItems'.",
+                ))
+            })?;
+
+        let mut page = YoutubePage {
+            results: vec![],
+            continuation: None,
+        };
+        for item in items {
+            if let Some(contents) = item.pointer("/itemSectionRenderer/contents") {
+                page.results.extend(parse_videos(contents)?);
+            } else if let Some(token) = item
+                .pointer("/continuationItemRenderer/continuationEndpoint/continuationCommand/token")
+                .and_then(Value::as_str)
+            {
+                page.continuation = Some(String::from(token));
+            }
+        }
+        Ok(page)
+    }
+
+    /// Gets the title and channel name of a video
+    pub fn video_info(video_id: &str) -> NeoResult<(String, String)> {
+        let resp = serde_json::from_str::<Value>(
+            &ureq::get("https://www.youtube.com/oembed")
+                .query(
+                    "url",
+                    &format!("https://www.youtube.com/watch?v={}", video_id),
+                )
+                .query("format", "json")
+                .call()?
+                .into_string()?,
+        )?;
+        let field = |name: &str| {
+            resp.get(name)
+                .and_then(Value::as_str)
+                .map(String::from)
+                .ok_or_else(|| Error::Other(format!("Parsing Error: Can't find '{}'.", name)))
+        };
+        Ok((field("title")?, field("author_name")?))
+    }
+
+    /// Lists the videos of a playlist with yt-dlp, without resolving each one.
+    /// Channel links list the channel's uploads
+    pub fn playlist(url: &str) -> NeoResult<YoutubePlaylist> {
+        let args = vec![
+            ytd_rs::Arg::new("--flat-playlist"),
+            ytd_rs::Arg::new("--dump-single-json"),
+        ];
+        let url = channel_videos(url);
+        let output = ytd_rs::YoutubeDL::new(&std::env::temp_dir(), args, &url)
+            .and_then(|ytd| ytd.download())
+            .map_err(|e| Error::Other(format!("yt-dlp Error: {:?}", e)))?;
+        let playlist = serde_json::from_str::<Value>(output.output())?;
+
+        Ok(YoutubePlaylist {
+            title: playlist
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or(&url)
+                .to_string(),
+            videos: playlist
+                .get("entries")
+                .and_then(Value::as_array)
+                .ok_or_else(|| Error::Other(String::from("Parsing Error: Can't find 'entries'.")))?
+                .iter()
+                .filter_map(|entry| {
+                    Some(YoutubeResult {
+                        title: entry.get("title")?.as_str()?.to_string(),
+                        href: entry.get("id")?.as_str()?.to_string(),
+                        channel: entry
+                            .get("channel")
+                            .or_else(|| entry.get("uploader"))
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        duration: entry
+                            .get("duration")
+                            .and_then(Value::as_f64)
+                            .map(Duration::from_secs_f64),
+                    })
+                })
+                .collect(),
+        })
+    }
+
+    /// Starts downloading a video in the background. Progress is reported through
+    /// `Event::DownloadProgress`, and `Event::DownloadReady` or `Event::DownloadFailed`
+    /// is sent once it can be played with `start`. A video that is already being
+    /// downloaded isn't downloaded a second time
+    pub fn play(&mut self, video_id: String) {
+        self.loading = Some(video_id.clone());
+        let tx = self.events.clone();
+
+        if let Some((path, duration)) = self.cache.get(&video_id) {
+            let download = Download::cached(video_id.clone(), path, duration);
+            let _ = tx.send(Event::DownloadReady(video_id, download));
+            return;
+        }
+        if let Some(download) = self
+            .download
+            .as_ref()
+            .filter(|download| download.video_id == video_id)
+        {
+            let _ = tx.send(Event::DownloadReady(video_id, download.clone()));
+            return;
+        }
+
+        let generation = GENERATION.fetch_add(1, Ordering::SeqCst);
+        {
+            let mut transcodes = self.transcodes.lock().unwrap();
+            // The download already running sends `Event::DownloadReady` when it can play
+            if transcodes.contains_key(&video_id) {
+                return;
+            }
+            transcodes.insert(
+                video_id.clone(),
+                Transcode {
+                    generation,
+                    ffmpeg: None,
+                },
+            );
+        }
+        let guard = Arc::new(TranscodeGuard {
+            transcodes: self.transcodes.clone(),
+            video_id: video_id.clone(),
+            generation,
+        });
+
+        let path = self.cache.path(&video_id);
+        thread::spawn(move || {
+            let event = match Download::start(&video_id, path, tx.clone(), &guard) {
+                Ok(download) => Event::DownloadReady(video_id, download),
+                // Cancelled downloads fail once ffmpeg is killed, which isn't worth reporting
+                Err(_) if !guard.is_current() => return,
+                Err(err) => Event::DownloadFailed(video_id, err.to_string()),
+            };
+            let _ = tx.send(event);
+        });
+    }
+
+    /// Plays a download sent by `Event::DownloadReady`, unless another video was
+    /// requested since. Returns whether it started playing
+    pub fn start(&mut self, video_id: &str, download: Download) -> NeoResult<bool> {
+        if !self.is_loading(video_id) {
+            return Ok(false);
+        }
+        self.loading = None;
+
+        let source = Decoder::new(BufReader::new(download.open()?))
+            .map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))?;
+
+        let sink = new_sink(&self.handle, &self.sink)?;
+        sink.append(BufferedSource::new(source));
+        sink.play();
+
+        self.sink = sink;
+        self.duration = download.duration;
+        self.cache.touch(video_id, download.duration)?;
+        self.download = Some(download);
+        self.loaded = true;
+        Ok(true)
+    }
+
+    /// Removes all cached audio except the video currently loaded in the sink
+    pub fn clear_cache(&mut self) -> NeoResult<()> {
+        let current = self
+            .download
+            .as_ref()
+            .map(|download| download.video_id.as_str());
+        self.cache.clear(current)
+    }
+
+    pub fn is_loading(&self, video_id: &str) -> bool {
+        self.loading.as_deref() == Some(video_id)
+    }
+
+    /// Stops waiting for the video being downloaded, killing its download unless
+    /// it's also the one playing
+    pub fn cancel(&mut self) {
+        if let Some(video_id) = self.loading.take() {
+            let playing = self
+                .download
+                .as_ref()
+                .map_or(false, |download| download.video_id == video_id);
+            if !playing {
+                kill(&self.transcodes, &video_id, None);
+            }
+        }
+    }
+
+    /// Whether nothing is playing and nothing is about to
+    pub fn is_idle(&self) -> bool {
+        self.loading.is_none() && self.sink.empty()
+    }
+
+    /// Rodio can't seek a playing source, so the current file is decoded again
+    /// from the requested position into a fresh sink that replaces the old one.
+    /// Nothing happens while the next video is still downloading
+    pub fn seek(&mut self, position: Duration) -> NeoResult<()> {
+        if self.loading.is_some() || !self.loaded {
+            return Ok(());
+        }
+        if let Some(download) = &self.download {
+            let source = Decoder::new(BufReader::new(download.open()?))
+                .map_err(|e| Error::Other(format!("Rodio Error: {:?}", e)))?;
+
+            let sink = new_sink(&self.handle, &self.sink)?;
+            sink.append(BufferedSource::new(source.skip_duration(position)));
+
+            self.sink = sink;
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.sink.pause();
+    }
+
+    /// Replaces the sink with an empty one. The download is kept, so the same
+    /// video can start again without downloading it twice
+    pub fn stop(&mut self) -> NeoResult<()> {
+        self.sink = new_sink(&self.handle, &self.sink)?;
+        self.loaded = false;
+        self.duration = None;
+        Ok(())
+    }
+
+    pub fn resume(&mut self) {
+        self.sink.play();
+    }
+
+    /// Sets the sink volume from a 0-100 percentage
+    pub fn set_volume(&mut self, volume: u8) {
+        self.sink.set_volume(volume as f32 / 100.0);
+    }
+}
+
+impl Drop for YoutubeClient {
+    /// ffmpeg would otherwise keep downloading after the app quits
+    fn drop(&mut self) {
+        for (_, transcode) in self.transcodes.lock().unwrap().drain() {
+            if let Some(mut ffmpeg) = transcode.ffmpeg {
+                let _ = ffmpeg.kill();
+                let _ = ffmpeg.wait();
+            }
+        }
+    }
+}
+
+/// Kills the ffmpeg process of a download, unless it already exited or, when
+/// `generation` is given, the video is being downloaded again by a newer one
+fn kill(transcodes: &Transcodes, video_id: &str, generation: Option<u64>) {
+    let mut transcodes = transcodes.lock().unwrap();
+    let current = transcodes
+        .get(video_id)
+        .map(|transcode| transcode.generation);
+    if current.is_some() && generation.map_or(true, |generation| current == Some(generation)) {
+        if let Some(mut ffmpeg) = transcodes
+            .remove(video_id)
+            .and_then(|transcode| transcode.ffmpeg)
+        {
+            let _ = ffmpeg.kill();
+            let _ = ffmpeg.wait();
+        }
+    }
+}
+
+/// Shared by the handles to a download, killing it once none of them are left
+#[derive(Debug)]
+struct TranscodeGuard {
+    transcodes: Transcodes,
+    video_id: String,
+    generation: u64,
+}
+
+impl TranscodeGuard {
+    /// Whether the download is still running and wasn't cancelled
+    fn is_current(&self) -> bool {
+        self.transcodes
+            .lock()
+            .unwrap()
+            .get(&self.video_id)
+            .map_or(false, |transcode| transcode.generation == self.generation)
+    }
+}
+
+impl Drop for TranscodeGuard {
+    fn drop(&mut self) {
+        kill(&self.transcodes, &self.video_id, Some(self.generation));
+    }
+}
+
+/// Audio of a video being transcoded to mp3 by ffmpeg as it downloads
+#[derive(Clone, Debug)]
+pub struct Download {
+    video_id: String,
+    /// Where the audio ends up once the download is finished
+    path: PathBuf,
+    /// Where ffmpeg writes the audio while downloading
+    part: PathBuf,
+    finished: Arc<AtomicBool>,
+    /// Length of the video, as reported by yt-dlp
+    duration: Option<Duration>,
+    /// `None` for audio that was already cached
+    guard: Option<Arc<TranscodeGuard>>,
+}
+
+impl Download {
+    /// Resolves the audio stream with yt-dlp and has ffmpeg download it, blocking
+    /// until there is enough audio on disk to start playing
+    fn start(
+        video_id: &str,
+        path: PathBuf,
+        events: Sender<Event<Key>>,
+        guard: &Arc<TranscodeGuard>,
+    ) -> NeoResult<Self> {
+        let args = vec![
+            ytd_rs::Arg::new("--quiet"),
+            ytd_rs::Arg::new_with_arg("--format", "bestaudio"),
+            ytd_rs::Arg::new_with_arg("--print", "duration"),
+            ytd_rs::Arg::new_with_arg("--print", "urls"),
+        ];
+        let dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        let output = ytd_rs::YoutubeDL::new(
+            &dir,
+            args,
+            format!("https://www.youtube.com/watch?v={}", video_id).as_str(),
+        )
+        .and_then(|ytd| ytd.download())
+        .map_err(|e| Error::Other(format!("yt-dlp Error: {:?}", e)))?;
+        let mut lines = output.output().lines();
+
+        let duration = lines
+            .next()
+            .and_then(|duration| duration.trim().parse::<f64>().ok())
+            .map(Duration::from_secs_f64);
+        let url = lines
+            .next()
+            .ok_or_else(|| Error::Other(String::from("yt-dlp returned no stream.")))?
+            .trim();
+
+        // Each download has its own part file, named after the process and the generation
+        let part = path.with_extension(format!("{}-{}.part", process::id(), guard.generation));
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(&[
+                "-loglevel",
+                "quiet",
+                "-nostats",
+                "-progress",
+                "pipe:1",
+                "-y",
+            ])
+            .args(&["-i", url, "-vn", "-f", "mp3"])
+            .arg(&part)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdout = ffmpeg.stdout.take();
+        {
+            let mut transcodes = guard.transcodes.lock().unwrap();
+            match transcodes.get_mut(video_id) {
+                Some(transcode) if transcode.generation == guard.generation => {
+                    transcode.ffmpeg = Some(ffmpeg);
+                }
+                // Cancelled while yt-dlp was resolving the stream
+                _ => {
+                    let _ = ffmpeg.kill();
+                    let _ = ffmpeg.wait();
+                    let _ = fs::remove_file(&part);
+                    return Err(Error::Other(String::from("Download cancelled.")));
+                }
+            }
+        }
+
+        let finished = Arc::new(AtomicBool::new(false));
+        {
+            let video_id = String::from(video_id);
+            let (path, part, finished) = (path.clone(), part.clone(), finished.clone());
+            let (transcodes, generation) = (guard.transcodes.clone(), guard.generation);
+            thread::spawn(move || {
+                // ffmpeg reports its progress as a block of `key=value` lines ending with `progress=`
+                let mut percent = 0.0;
+                let mut speed = String::new();
+                for line in stdout
+                    .into_iter()
+                    .flat_map(|out| BufReader::new(out).lines().flatten())
+                {
+                    match line.split_once('=') {
+                        Some(("out_time_ms", time)) => {
+                            if let (Ok(time), Some(duration)) = (time.parse::<u64>(), duration) {
+                                // Despite the name, ffmpeg reports this in microseconds
+                                percent = (Duration::from_micros(time).as_secs_f64()
+                                    / duration.as_secs_f64()
+                                    * 100.0)
+                                    .min(100.0);
+                            }
+                        }
+                        Some(("speed", value)) => speed = String::from(value.trim()),
+                        Some(("progress", _)) => {
+                            let _ = events.send(Event::DownloadProgress(DownloadProgress {
+                                video_id: video_id.clone(),
+                                percent,
+                                speed: speed.clone(),
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+
+                // Killed downloads were already waited for by `kill`. The entry itself
+                // stays until the download is dropped, so failures still count as current
+                let ffmpeg = match transcodes.lock().unwrap().get_mut(&video_id) {
+                    Some(transcode) if transcode.generation == generation => {
+                        transcode.ffmpeg.take()
+                    }
+                    _ => None,
+                };
+                match ffmpeg.map(|mut ffmpeg| ffmpeg.wait()) {
+                    Some(Ok(status)) if status.success() => {
+                        let _ = fs::rename(&part, &path);
+                    }
+                    _ => {
+                        let _ = fs::remove_file(&part);
+                    }
+                }
+                finished.store(true, Ordering::SeqCst);
+            });
+        }
+
+        let download = Self {
+            video_id: String::from(video_id),
+            path,
+            part,
+            finished,
+            duration,
+            guard: Some(guard.clone()),
+        };
+        download.wait_until_ready()?;
+        Ok(download)
+    }
+
+    /// Audio that was already fully downloaded
+    fn cached(video_id: String, path: PathBuf, duration: Option<Duration>) -> Self {
+        Self {
+            video_id,
+            part: path.with_extension("part"),
+            path,
+            finished: Arc::new(AtomicBool::new(true)),
+            duration,
+            guard: None,
+        }
+    }
+
+    /// Blocks until enough audio was written to start decoding, or the download is over
+    fn wait_until_ready(&self) -> NeoResult<()> {
+        loop {
+            let finished = self.finished.load(Ordering::SeqCst);
+            if finished {
+                return if self.path.exists() {
+                    Ok(())
+                } else {
+                    Err(Error::Other(String::from("Download failed.")))
+                };
+            }
+            if fs::metadata(&self.part).map_or(false, |meta| meta.len() >= READY_SIZE) {
+                return Ok(());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Opens the audio for reading, waiting for ffmpeg to create the file if needed
+    fn open(&self) -> NeoResult<StreamingFile> {
+        loop {
+            let finished = self.finished.load(Ordering::SeqCst);
+            if let Ok(file) = File::open(&self.part).or_else(|_| File::open(&self.path)) {
+                return Ok(StreamingFile {
+                    file,
+                    finished: self.finished.clone(),
+                });
+            }
+            if finished {
+                return Err(Error::Other(String::from("Download failed.")));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// A file that may still be growing. Reads that reach the end wait for more
+/// data until the download is finished, so decoding can start on the first bytes.
+/// Those waits block, so it's only read from `BufferedSource`'s decoding thread
+struct StreamingFile {
+    file: File,
+    finished: Arc<AtomicBool>,
+}
+
+impl Read for StreamingFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // Checked before reading so the last bytes written aren't missed
+            let finished = self.finished.load(Ordering::SeqCst);
+            let read = self.file.read(buf)?;
+            if read > 0 || finished || buf.is_empty() {
+                return Ok(read);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Seek for StreamingFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // The end isn't known until the whole file is written
+        if let SeekFrom::End(_) = pos {
+            while !self.finished.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+        self.file.seek(pos)
+    }
+}
+
+/// Audio decoded on its own thread and handed over in chunks, since decoding a
+/// `StreamingFile` can wait for the download and rodio's output thread can't.
+/// Plays silence whenever the download falls behind
+struct BufferedSource {
+    chunks: Receiver<Vec<i16>>,
+    chunk: std::vec::IntoIter<i16>,
+    /// Samples of silence left to play, always whole frames so channels stay in order
+    silence: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl BufferedSource {
+    fn new<S>(source: S) -> Self
+    where
+        S: Source<Item = i16> + Send + 'static,
+    {
+        let channels = source.channels();
+        let sample_rate = source.sample_rate();
+        let (tx, rx) = mpsc::sync_channel(BUFFERED_CHUNKS);
+        thread::spawn(move || {
+            let mut source = source.peekable();
+            let size = CHUNK_FRAMES * channels as usize;
+            while source.peek().is_some() {
+                let chunk = source.by_ref().take(size).collect::<Vec<i16>>();
+                // The source was dropped, because the track changed or was seeked
+                if tx.send(chunk).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            chunks: rx,
+            chunk: vec![].into_iter(),
+            silence: 0,
+            channels,
+            sample_rate,
+        }
+    }
+}
+
+impl Iterator for BufferedSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        loop {
+            if self.silence > 0 {
+                self.silence -= 1;
+                return Some(0);
+            }
+            if let Some(sample) = self.chunk.next() {
+                return Some(sample);
+            }
+            match self.chunks.try_recv() {
+                Ok(chunk) => self.chunk = chunk.into_iter(),
+                Err(TryRecvError::Empty) => self.silence = self.channels as usize,
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Source for BufferedSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Points links to a channel without a tab, like `/@name`, at its videos tab,
+/// since yt-dlp lists a channel's tabs instead of its videos otherwise. Links
+/// to other tabs, like `/@name/shorts` or `/channel/<id>/streams`, are kept
+fn channel_videos(url: &str) -> String {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, format!("?{}", query)),
+        None => (url, String::new()),
+    };
+    let path = path.trim_end_matches('/');
+    let segments = path.split('/').collect::<Vec<&str>>();
+    let tab = segments
+        .iter()
+        .position(|segment| segment.starts_with('@') || ["channel", "c", "user"].contains(segment))
+        // `/@name` is followed by the tab, `/channel/<id>` and the others by the name first
+        .map(|channel| {
+            if segments[channel].starts_with('@') {
+                channel + 1
+            } else {
+                channel + 2
+            }
+        });
+    match tab {
+        Some(tab) if tab == segments.len() => format!("{}/videos{}", path, query),
+        _ => String::from(url),
+    }
+}
+
+/// Parses the videos in the `contents` of a search results section, skipping
+/// channels, playlists and ads
+fn parse_videos(contents: &Value) -> NeoResult<Vec<YoutubeResult>> {
+    contents
         .as_array()
         .ok_or_else(|| Error::Other(String::from("Parsing Error: Not an array.")))?
         .iter()
@@ -73,6 +838,15 @@ impl YoutubeClient {
                                 })?
                                 .to_string()
                                 .replace('\"', ""),
+                            channel: obj
+                                .pointer("/ownerText/runs/0/text")
+                                .and_then(Value::as_str)
+                                .unwrap_or_default()
+                                .to_string(),
+                            duration: obj
+                                .pointer("/lengthText/simpleText")
+                                .and_then(Value::as_str)
+                                .and_then(parse_length),
                         })
                     })
             } else {
@@ -80,39 +854,68 @@ impl YoutubeClient {
             }
         })
         .collect::<NeoResult<Vec<YoutubeResult>>>()
-    }
-
-    pub async fn play(&mut self, video_id: String) {
-        let args = vec![
-            ytd_rs::Arg::new("--quiet"),
-            ytd_rs::Arg::new("-x"),
-            ytd_rs::Arg::new_with_arg("--output", format!("{}.%(ext)s", video_id).as_str()),
-            ytd_rs::Arg::new_with_arg("--audio-format", "mp3"),
-        ];
-        let path = std::path::PathBuf::from("./audio");
-        let ytd = ytd_rs::YoutubeDL::new(
-            &path,
-            args,
-            format!("https://www.youtube.com/watch?v={}", video_id).as_str(),
-        )
-        .unwrap();
+}
 
-        // start download
-        ytd.download().unwrap();
+/// Parses a video length as YouTube displays it, like `1:02:03` or `4:05`
+fn parse_length(length: &str) -> Option<Duration> {
+    length
+        .split(':')
+        .try_fold(0, |secs, part| Some(secs * 60 + part.parse::<u64>().ok()?))
+        .map(Duration::from_secs)
+}
 
-        let file = BufReader::new(File::open(format!("./audio/{}.mp3", video_id)).unwrap());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let source = Decoder::new(file).unwrap();
+    #[test]
+    fn channel_without_tab_lists_videos() {
+        assert_eq!(
+            channel_videos("https://www.youtube.com/@name"),
+            "https://www.youtube.com/@name/videos"
+        );
+        assert_eq!(
+            channel_videos("https://www.youtube.com/@name/"),
+            "https://www.youtube.com/@name/videos"
+        );
+        assert_eq!(
+            channel_videos("https://www.youtube.com/channel/UC123"),
+            "https://www.youtube.com/channel/UC123/videos"
+        );
+        assert_eq!(
+            channel_videos("https://www.youtube.com/c/name"),
+            "https://www.youtube.com/c/name/videos"
+        );
+    }
 
-        self.sink.append(source);
-        self.sink.play();
+    #[test]
+    fn channel_tabs_are_kept() {
+        for url in [
+            "https://www.youtube.com/@name/shorts",
+            "https://www.youtube.com/@name/streams",
+            "https://www.youtube.com/@name/videos",
+            "https://www.youtube.com/channel/UC123/streams",
+            "https://www.youtube.com/user/name/videos",
+        ] {
+            assert_eq!(channel_videos(url), url);
+        }
     }
 
-    pub fn pause(&mut self) {
-        self.sink.pause();
+    #[test]
+    fn channel_query_is_kept() {
+        assert_eq!(
+            channel_videos("https://www.youtube.com/@name?si=abc"),
+            "https://www.youtube.com/@name/videos?si=abc"
+        );
+        assert_eq!(
+            channel_videos("https://www.youtube.com/@name/shorts?si=abc"),
+            "https://www.youtube.com/@name/shorts?si=abc"
+        );
     }
 
-    pub fn resume(&mut self) {
-        self.sink.play();
+    #[test]
+    fn playlists_are_not_channels() {
+        let url = "https://www.youtube.com/playlist?list=PL123";
+        assert_eq!(channel_videos(url), url);
     }
 }